@@ -0,0 +1,431 @@
+use crate::{
+    vector::{Point3, Vec3},
+    HitRecord, Hittable, Ray, Shape, SHADOW_BIAS, TOLERANCE,
+};
+
+const BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+const TRAVERSAL_COST: f64 = 1.0;
+const INTERSECTION_COST: f64 = 1.0;
+/// Median splits below this depth halve the primitives at every level, so
+/// even 2^32 of them leave the tree less than `STACK_SIZE` deep
+const SAH_DEPTH: usize = 24;
+const STACK_SIZE: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    pub fn new(min: Point3, max: Point3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            min: Point3::splat(f64::INFINITY),
+            max: Point3::splat(f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn grow(&self, point: Point3) -> Aabb {
+        Aabb::new(self.min.min(point), self.max.max(point))
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        self.min.lerp(self.max, 0.5)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let d = self.max - self.min;
+        (0..3).max_by(|&a, &b| d[a].total_cmp(&d[b])).unwrap()
+    }
+
+    /// Slab test, returns the distance at which the ray enters the box
+    fn hit(&self, ray: &Ray, inverse_direction: Vec3, t_max: f64) -> Option<f64> {
+        let mut t0 = 0.0_f64;
+        let mut t1 = t_max;
+        for i in 0..3 {
+            let near = (self.min[i] - ray.origin[i]) * inverse_direction[i];
+            let far = (self.max[i] - ray.origin[i]) * inverse_direction[i];
+            let (near, far) = if near < far { (near, far) } else { (far, near) };
+            t0 = t0.max(near);
+            t1 = t1.min(far);
+            if t0 > t1 {
+                return None;
+            }
+        }
+        Some(t0)
+    }
+}
+
+/// A leaf has `count > 0` and owns `shapes[offset..offset + count]`;
+/// an interior node's first child follows it, the second lives at `offset`
+#[derive(Debug)]
+struct Node {
+    bounds: Aabb,
+    offset: usize,
+    count: usize,
+    axis: usize,
+}
+
+/// Bounding volume hierarchy over the scene shapes, flattened depth first
+pub(crate) struct Bvh<'a> {
+    shapes: Vec<Shape<'a>>,
+    nodes: Vec<Node>,
+}
+
+struct Primitive {
+    index: usize,
+    bounds: Aabb,
+    centroid: Point3,
+}
+
+impl<'a> Bvh<'a> {
+    pub fn new(shapes: Vec<Shape<'a>>) -> Self {
+        let mut primitives = shapes
+            .iter()
+            .enumerate()
+            .map(|(index, shape)| {
+                let bounds = shape.bounds();
+                let centroid = bounds.centroid();
+                Primitive {
+                    index,
+                    bounds,
+                    centroid,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut nodes = Vec::with_capacity(2 * shapes.len());
+        if !primitives.is_empty() {
+            build(&mut primitives, 0, &mut nodes, 0);
+        }
+
+        let mut slots = shapes.into_iter().map(Some).collect::<Vec<_>>();
+        let shapes = primitives
+            .iter()
+            .map(|p| slots[p.index].take().unwrap())
+            .collect();
+
+        Self { shapes, nodes }
+    }
+
+    /// Closest shape hit by the ray
+    pub fn hit(&self, ray: &Ray) -> Option<HitRecord<'_>> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inverse_direction = ray.direction.map(|d| 1.0 / d);
+
+        let mut closest: Option<HitRecord> = None;
+        let mut stack = Stack::new(0);
+        while let Some(index) = stack.pop() {
+            let t_max = closest.map_or(f64::INFINITY, |record| record.t);
+            let node = &self.nodes[index];
+            if node.bounds.hit(ray, inverse_direction, t_max).is_none() {
+                continue;
+            }
+
+            if node.count > 0 {
+                for shape in &self.shapes[node.offset..node.offset + node.count] {
+                    let t_max = closest.map_or(f64::INFINITY, |record| record.t);
+                    if let Some(record) = shape.intersect(ray, TOLERANCE, t_max) {
+                        closest = Some(record);
+                    }
+                }
+            } else if inverse_direction[node.axis] < 0.0 {
+                // visit the near child first so t_max shrinks sooner
+                stack.push(index + 1);
+                stack.push(node.offset);
+            } else {
+                stack.push(node.offset);
+                stack.push(index + 1);
+            }
+        }
+
+        closest
+    }
+
+    /// Whether anything lies strictly between `origin` and the point
+    /// `distance` along the unit `direction`, which may be infinitely far.
+    /// Stops at the first blocker found rather than the closest one
+    pub fn occluded(&self, origin: Point3, direction: Vec3, distance: f64) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let ray = Ray::new(origin, direction);
+        let t_max = distance - SHADOW_BIAS;
+
+        let inverse_direction = ray.direction.map(|d| 1.0 / d);
+
+        let mut stack = Stack::new(0);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.bounds.hit(&ray, inverse_direction, t_max).is_none() {
+                continue;
+            }
+
+            if node.count > 0 {
+                let blocked = self.shapes[node.offset..node.offset + node.count]
+                    .iter()
+                    .any(|shape| shape.intersect(&ray, SHADOW_BIAS, t_max).is_some());
+                if blocked {
+                    return true;
+                }
+            } else {
+                stack.push(node.offset);
+                stack.push(index + 1);
+            }
+        }
+
+        false
+    }
+}
+
+/// Past `SAH_DEPTH` every split is a median split, which bounds the depth
+/// of the tree and so the traversal stack
+fn build(
+    primitives: &mut [Primitive],
+    offset: usize,
+    nodes: &mut Vec<Node>,
+    depth: usize,
+) -> usize {
+    let bounds = primitives
+        .iter()
+        .fold(Aabb::empty(), |b, p| b.union(&p.bounds));
+
+    let index = nodes.len();
+    nodes.push(Node {
+        bounds,
+        offset,
+        count: primitives.len(),
+        axis: 0,
+    });
+
+    if primitives.len() == 1 {
+        return index;
+    }
+
+    let centroid_bounds = primitives
+        .iter()
+        .fold(Aabb::empty(), |b, p| b.grow(p.centroid));
+    let axis = centroid_bounds.longest_axis();
+    let lo = centroid_bounds.min[axis];
+    let hi = centroid_bounds.max[axis];
+
+    if hi - lo <= 0.0 {
+        // every centroid coincides, no split can separate them
+        return index;
+    }
+
+    let bin_of = |p: &Primitive| {
+        let b = (BINS as f64 * (p.centroid[axis] - lo) / (hi - lo)) as usize;
+        b.min(BINS - 1)
+    };
+
+    let mut counts = [0usize; BINS];
+    let mut bins = [Aabb::empty(); BINS];
+    for p in primitives.iter() {
+        let b = bin_of(p);
+        counts[b] += 1;
+        bins[b] = bins[b].union(&p.bounds);
+    }
+
+    // cost of splitting after each bin, using the surface area heuristic
+    let mut best_cost = f64::INFINITY;
+    let mut best_split = 0;
+    for split in 0..BINS - 1 {
+        let (left, right) = bins.split_at(split + 1);
+        let (left_counts, right_counts) = counts.split_at(split + 1);
+        let left_count = left_counts.iter().sum::<usize>();
+        let right_count = right_counts.iter().sum::<usize>();
+        if left_count == 0 || right_count == 0 {
+            continue;
+        }
+        let left_area = left
+            .iter()
+            .fold(Aabb::empty(), |b, a| b.union(a))
+            .surface_area();
+        let right_area = right
+            .iter()
+            .fold(Aabb::empty(), |b, a| b.union(a))
+            .surface_area();
+        let cost = TRAVERSAL_COST
+            + INTERSECTION_COST * (left_count as f64 * left_area + right_count as f64 * right_area)
+                / bounds.surface_area();
+        if cost < best_cost {
+            best_cost = cost;
+            best_split = split;
+        }
+    }
+
+    let leaf_cost = INTERSECTION_COST * primitives.len() as f64;
+    if primitives.len() <= MAX_LEAF_SIZE && leaf_cost <= best_cost {
+        return index;
+    }
+
+    let mut mid = match depth {
+        ..SAH_DEPTH => partition(primitives, |p| bin_of(p) <= best_split),
+        _ => 0,
+    };
+    if mid == 0 || mid == primitives.len() {
+        // the binning failed to separate anything or the tree grows too deep,
+        // fall back to a median split
+        mid = primitives.len() / 2;
+        primitives
+            .select_nth_unstable_by(mid, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+    }
+
+    let (left, right) = primitives.split_at_mut(mid);
+    build(left, offset, nodes, depth + 1);
+    let second = build(right, offset + mid, nodes, depth + 1);
+
+    let node = &mut nodes[index];
+    node.offset = second;
+    node.count = 0;
+    node.axis = axis;
+
+    index
+}
+
+/// Nodes still to visit, kept on the stack of the caller rather than the
+/// heap since every ray needs one
+struct Stack {
+    items: [usize; STACK_SIZE],
+    len: usize,
+}
+
+impl Stack {
+    fn new(root: usize) -> Self {
+        let mut items = [0; STACK_SIZE];
+        items[0] = root;
+        Self { items, len: 1 }
+    }
+
+    fn push(&mut self, index: usize) {
+        self.items[self.len] = index;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<usize> {
+        self.len = self.len.checked_sub(1)?;
+        Some(self.items[self.len])
+    }
+}
+
+fn partition(primitives: &mut [Primitive], predicate: impl Fn(&Primitive) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..primitives.len() {
+        if predicate(&primitives[i]) {
+            primitives.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::{mesh::Mesh, sampling, vector::Color, Material, Sphere, Triangle};
+
+    static MATERIAL: Material = Material {
+        diffuse: Color::splat(0.5),
+        specular: Color::splat(0.0),
+        brightness: 0.0,
+        reflectivity: 0.0,
+        transmissivity: 0.0,
+        ior: 1.0,
+        emission: Color::splat(0.0),
+        texture: None,
+    };
+
+    fn vector(rng: &mut SmallRng, size: f64) -> Vec3 {
+        Vec3::new(
+            rng.gen_range(-size..size),
+            rng.gen_range(-size..size),
+            rng.gen_range(-size..size),
+        )
+    }
+
+    fn point(rng: &mut SmallRng, size: f64) -> Point3 {
+        Point3::default() + vector(rng, size)
+    }
+
+    /// Hits and shadow queries through the hierarchy match testing every
+    /// shape in turn
+    #[test]
+    fn agrees_with_testing_every_shape() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut mesh = Mesh::default();
+        for _ in 0..200 {
+            let corner = point(&mut rng, 10.0);
+            mesh.push(
+                [
+                    corner,
+                    corner + vector(&mut rng, 1.5),
+                    corner + vector(&mut rng, 1.5),
+                ],
+                0,
+            );
+        }
+
+        let mut shapes = vec![];
+        for _ in 0..200 {
+            let sphere = Sphere::new(
+                shapes.len(),
+                point(&mut rng, 10.0),
+                rng.gen_range(0.1..1.0),
+                &MATERIAL,
+            );
+            shapes.push(Shape::Sphere(sphere));
+        }
+        for face in 0..mesh.faces.len() {
+            shapes.push(Shape::Triangle(Triangle::new(
+                shapes.len(),
+                &mesh,
+                face,
+                &MATERIAL,
+            )));
+        }
+        let bvh = Bvh::new(shapes.clone());
+
+        for _ in 0..2000 {
+            let ray = Ray::new(point(&mut rng, 12.0), sampling::uniform_sphere(&mut rng));
+            let linear = shapes
+                .iter()
+                .filter_map(|shape| shape.intersect(&ray, TOLERANCE, f64::INFINITY))
+                .min_by(|a, b| a.t.total_cmp(&b.t));
+            let hit = bvh.hit(&ray);
+            assert_eq!(hit.map(|record| record.id), linear.map(|record| record.id));
+            assert_eq!(hit.map(|record| record.t), linear.map(|record| record.t));
+
+            let distance = rng.gen_range(0.0..20.0);
+            let blocked = shapes.iter().any(|shape| {
+                shape
+                    .intersect(&ray, SHADOW_BIAS, distance - SHADOW_BIAS)
+                    .is_some()
+            });
+            assert_eq!(bvh.occluded(ray.origin, ray.direction, distance), blocked);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use rand::Rng;
+
+use crate::{
+    sampling,
+    vector::{Point3, Vec3},
+    Ray,
+};
+
+/// Thin lens perspective camera, like the rest of the renderer it is
+/// left-handed: x to the right, y up and the view direction along +z
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera {
+    origin: Point3,
+    top_left: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    right: Vec3,
+    up: Vec3,
+    lens_radius: f64,
+}
+
+impl Camera {
+    /// `vfov` is the vertical field of view in degrees and `aspect_ratio`
+    /// the image width over its height. Points `focus_distance` away from
+    /// `look_from` are sharp, an `aperture` of zero makes it a pinhole
+    pub fn new(
+        look_from: Point3,
+        look_at: Point3,
+        up: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_distance: f64,
+    ) -> Self {
+        let half_height = (vfov.to_radians() / 2.0).tan();
+        let half_width = aspect_ratio * half_height;
+
+        let forward = (look_at - look_from).unit();
+        let right = up.cross(forward).unit();
+        let up = forward.cross(right);
+
+        let top_left =
+            look_from + (forward - right * half_width + up * half_height) * focus_distance;
+
+        Self {
+            origin: look_from,
+            top_left,
+            horizontal: right * 2.0 * half_width * focus_distance,
+            vertical: -up * 2.0 * half_height * focus_distance,
+            right,
+            up,
+            lens_radius: aperture / 2.0,
+        }
+    }
+
+    /// Ray through the image plane at `(s, t)`, where both range over `[0, 1]`
+    /// starting from the top left corner, leaving from a random point on the lens
+    pub fn ray(&self, s: f64, t: f64, rng: &mut impl Rng) -> Ray {
+        let lens = sampling::unit_disk(rng) * self.lens_radius;
+        let origin = self.origin + self.right * lens.x + self.up * lens.y;
+        let target = self.top_left + self.horizontal * s + self.vertical * t;
+        Ray::new(origin, (target - origin).unit())
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::{light::Illumination, shape::Shape, vector::Point3, HitRecord};
+
+/// The shapes whose material gives off light, sampled like any other light
+/// so that small or distant ones don't depend on being hit by chance
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Emitters<'a> {
+    shapes: Vec<Shape<'a>>,
+    /// Running total of area times power over `shapes`, ending at one
+    cdf: Vec<f64>,
+    /// Position in `shapes` of each emissive shape id
+    positions: HashMap<usize, usize>,
+}
+
+impl<'a> Emitters<'a> {
+    pub(crate) fn new(shapes: &[Shape<'a>]) -> Self {
+        let mut emitters = Emitters::default();
+        let mut total = 0.0;
+        for shape in shapes {
+            let Some(material) = shape.material() else {
+                continue;
+            };
+            let emission = material.emission;
+            let power = shape.area() * emission.average();
+            if power > 0.0 {
+                emitters.positions.insert(shape.id(), emitters.shapes.len());
+                emitters.shapes.push(shape.clone());
+                total += power;
+                emitters.cdf.push(total);
+            }
+        }
+        for sum in &mut emitters.cdf {
+            *sum /= total;
+        }
+        emitters
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// Light arriving at `point` from one of the shapes, picked in
+    /// proportion to its area times power and then sampled uniformly over
+    /// its surface
+    pub(crate) fn illuminate(&self, point: Point3, rng: &mut impl Rng) -> Option<Illumination> {
+        let choice = rng.gen::<f64>();
+        let k = self
+            .cdf
+            .partition_point(|&sum| sum <= choice)
+            .min(self.shapes.len().checked_sub(1)?);
+        let shape = &self.shapes[k];
+        let (target, normal) = shape.sample(rng)?;
+
+        let to_light = target - point;
+        let distance2 = to_light.length_squared();
+        let distance = distance2.sqrt();
+        let direction = to_light / distance;
+        // both sides of a surface glow
+        let cos = direction.dot(normal).abs();
+        if cos <= 0.0 {
+            return None;
+        }
+
+        let pdf = self.probability(k) * distance2 / (shape.area() * cos);
+        Some(Illumination {
+            direction,
+            distance,
+            irradiance: shape.material()?.emission / pdf,
+            pdf: Some(pdf),
+        })
+    }
+
+    /// Solid angle density with which `illuminate` picks the direction from
+    /// `point` towards the hit in `record`, zero if it did not hit an emitter
+    pub(crate) fn pdf(&self, point: Point3, record: &HitRecord) -> f64 {
+        let Some(&k) = self.positions.get(&record.id) else {
+            return 0.0;
+        };
+        let to_light = record.point - point;
+        let distance2 = to_light.length_squared();
+        let cos = to_light.dot(record.geometric_normal).abs() / distance2.sqrt();
+        if cos <= 0.0 {
+            return 0.0;
+        }
+        self.probability(k) * distance2 / (self.shapes[k].area() * cos)
+    }
+
+    fn probability(&self, k: usize) -> f64 {
+        match k {
+            0 => self.cdf[0],
+            _ => self.cdf[k] - self.cdf[k - 1],
+        }
+    }
+}
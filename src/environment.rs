@@ -0,0 +1,131 @@
+use std::{f64::consts::PI, path::Path};
+
+use image::ImageResult;
+use rand::Rng;
+
+use crate::{
+    sampling::Distribution,
+    texture::Texture,
+    vector::{Color, Vec3},
+};
+
+/// An equirectangular image of everything infinitely far away, as seen from
+/// the scene. The middle of the image lies straight ahead along +z and its
+/// top edge straight up
+#[derive(Clone, Debug, PartialEq)]
+pub struct Environment {
+    texture: Texture,
+    /// About the vertical axis, in radians
+    rotation: f64,
+    intensity: f64,
+    /// Rows picked by how much light they hold
+    rows: Distribution,
+    /// Columns within each row, picked the same way
+    columns: Vec<Distribution>,
+}
+
+impl Environment {
+    /// Loads a high dynamic range image such as Radiance `.hdr` or OpenEXR,
+    /// turned `rotation` degrees about the vertical axis and scaled by
+    /// `intensity`
+    pub fn load(path: &Path, rotation: f64, intensity: f64) -> ImageResult<Environment> {
+        Ok(Environment::new(Texture::load(path)?, rotation, intensity))
+    }
+
+    pub(crate) fn new(texture: Texture, rotation: f64, intensity: f64) -> Environment {
+        let (width, height) = (texture.width(), texture.height());
+
+        // lookups blend in the neighbouring texels, so each texel is weighted
+        // by the brightest of them to never miss a small bright light. Rows
+        // stop at the poles while columns wrap around
+        let brightest = |i: usize, j: usize| {
+            let mut max = 0.0_f64;
+            for row in j.saturating_sub(1)..=(j + 1).min(height - 1) {
+                for di in [width - 1, 0, 1] {
+                    let texel = texture.texel((i + di) % width, row);
+                    max = max.max(luminance(texel));
+                }
+            }
+            max
+        };
+        // rows near the poles cover less of the sphere
+        let columns = (0..height)
+            .map(|j| {
+                let sin = (PI * (j as f64 + 0.5) / height as f64).sin();
+                let weights = (0..width).map(|i| brightest(i, j) * sin).collect();
+                Distribution::new(weights)
+            })
+            .collect::<Vec<_>>();
+        let rows = Distribution::new(columns.iter().map(Distribution::total).collect());
+
+        Environment {
+            texture,
+            rotation: rotation.to_radians(),
+            intensity,
+            rows,
+            columns,
+        }
+    }
+
+    /// Radiance arriving from the unit `direction`
+    pub(crate) fn radiance(&self, direction: Vec3) -> Color {
+        let (u, v) = self.uv(direction);
+        self.texture.sample_rows_clamped((u, v)) * self.intensity
+    }
+
+    /// Unit direction drawn in proportion to the light arriving from it,
+    /// together with its solid angle density
+    pub(crate) fn sample(&self, rng: &mut impl Rng) -> Option<(Vec3, f64)> {
+        let (row, y, row_pdf) = self.rows.sample(rng.gen());
+        let (_, x, column_pdf) = self.columns[row].sample(rng.gen());
+
+        let sin = (PI * y).sin();
+        if sin <= 0.0 {
+            return None;
+        }
+        let pdf = row_pdf * column_pdf / (2.0 * PI * PI * sin);
+        Some((rotate(direction((x, 1.0 - y)), self.rotation), pdf))
+    }
+
+    /// Solid angle density with which `sample` picks the unit `direction`
+    pub(crate) fn pdf(&self, direction: Vec3) -> f64 {
+        let (u, v) = self.uv(direction);
+        let (width, height) = (self.texture.width(), self.texture.height());
+        let column = ((u * width as f64) as usize).min(width - 1);
+        let row = (((1.0 - v) * height as f64) as usize).min(height - 1);
+
+        let sin = (PI * (1.0 - v)).sin();
+        if sin <= 0.0 {
+            return 0.0;
+        }
+        self.rows.pdf(row) * self.columns[row].pdf(column) / (2.0 * PI * PI * sin)
+    }
+
+    /// Image coordinates of the unit `direction`, `v` growing upwards
+    fn uv(&self, direction: Vec3) -> (f64, f64) {
+        let local = rotate(direction, -self.rotation);
+        let u = 0.5 + local.x.atan2(local.z) / (2.0 * PI);
+        let v = 1.0 - local.y.clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+}
+
+/// Unit direction at image coordinates `uv`, the inverse of `Environment::uv`
+/// without rotation
+pub(crate) fn direction((u, v): (f64, f64)) -> Vec3 {
+    let theta = PI * (1.0 - v);
+    let phi = 2.0 * PI * (u - 0.5);
+    let sin = theta.sin();
+    Vec3::new(sin * phi.sin(), theta.cos(), sin * phi.cos())
+}
+
+/// Turns `v` by `angle` radians about the y axis, taking +z towards +x
+fn rotate(v: Vec3, angle: f64) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    Vec3::new(v.x * cos + v.z * sin, v.y, v.z * cos - v.x * sin)
+}
+
+/// Rec. 709 weights
+fn luminance(color: Color) -> f64 {
+    0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b
+}
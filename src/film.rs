@@ -0,0 +1,233 @@
+use std::{f64::consts::PI, ops::Range, str::FromStr};
+
+use crate::vector::Color;
+
+/// Reconstruction filters, all separable and evaluated in pixel units
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Box,
+    Tent,
+    Gaussian { alpha: f64 },
+    MitchellNetravali { b: f64, c: f64 },
+    Lanczos,
+}
+
+impl Filter {
+    pub fn evaluate(&self, x: f64, y: f64, radius: f64) -> f64 {
+        self.evaluate_1d(x, radius) * self.evaluate_1d(y, radius)
+    }
+
+    /// Narrowest radius in pixels the filter works with. Below it some
+    /// pixels get no samples or, with negative lobes squeezed close to the
+    /// center, weights that cancel out
+    pub fn min_radius(&self) -> f64 {
+        match self {
+            Filter::Box | Filter::Tent | Filter::Gaussian { .. } => 0.5,
+            Filter::MitchellNetravali { .. } => 2.0,
+            Filter::Lanczos => 1.0,
+        }
+    }
+
+    fn evaluate_1d(&self, x: f64, radius: f64) -> f64 {
+        let x = x.abs();
+        if x > radius {
+            return 0.0;
+        }
+
+        match *self {
+            Filter::Box => 1.0,
+            Filter::Tent => radius - x,
+            Filter::Gaussian { alpha } => {
+                ((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.0)
+            }
+            Filter::MitchellNetravali { b, c } => mitchell_netravali(2.0 * x / radius, b, c),
+            Filter::Lanczos => sinc(x) * sinc(x / radius),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    /// Parses a filter name, using the customary parameters for each
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "box" => Ok(Filter::Box),
+            "tent" => Ok(Filter::Tent),
+            "gaussian" => Ok(Filter::Gaussian { alpha: 2.0 }),
+            "mitchell" => Ok(Filter::MitchellNetravali {
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            }),
+            "lanczos" => Ok(Filter::Lanczos),
+            _ => Err(format!(
+                "unknown filter {name:?}, expected box, tent, gaussian, mitchell or lanczos"
+            )),
+        }
+    }
+}
+
+/// The cubic is defined over `[0, 2]`, callers rescale to the filter radius
+fn mitchell_netravali(x: f64, b: f64, c: f64) -> f64 {
+    let polynomial = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+            + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+            + (6.0 - 2.0 * b)
+    } else if x < 2.0 {
+        (-b - 6.0 * c) * x.powi(3)
+            + (6.0 * b + 30.0 * c) * x.powi(2)
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)
+    } else {
+        0.0
+    };
+    polynomial / 6.0
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1E-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Accumulates filter weighted samples, pixel `(i, j)` is centered at
+/// `(i + 0.5, j + 0.5)` in continuous film coordinates
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    filter: Filter,
+    radius: f64,
+    sums: Vec<Color>,
+    weights: Vec<f64>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Filter, radius: f64) -> Self {
+        Self {
+            width,
+            height,
+            filter,
+            radius,
+            sums: vec![Color::default(); width * height],
+            weights: vec![0.0; width * height],
+        }
+    }
+
+    /// A tile covering `rows` plus the margin their samples can reach,
+    /// so that rows can be rendered in parallel and merged afterwards
+    pub fn tile(&self, rows: Range<usize>) -> FilmTile {
+        let margin = self.radius.ceil() as usize;
+        let top = rows.start.saturating_sub(margin);
+        let bottom = (rows.end + margin).min(self.height);
+        let size = (bottom - top) * self.width;
+        FilmTile {
+            width: self.width,
+            top,
+            bottom,
+            filter: self.filter,
+            radius: self.radius,
+            sums: vec![Color::default(); size],
+            weights: vec![0.0; size],
+        }
+    }
+
+    pub fn merge(&mut self, tile: FilmTile) {
+        let offset = tile.top * self.width;
+        for (k, (sum, weight)) in tile.sums.into_iter().zip(tile.weights).enumerate() {
+            self.sums[offset + k] += sum;
+            self.weights[offset + k] += weight;
+        }
+    }
+
+    /// Weighted averages per pixel, reusing the sums to hold them. Negative
+    /// lobes can leave a total weight below zero, which still averages
+    pub fn into_pixels(mut self) -> Vec<Color> {
+        for (sum, weight) in self.sums.iter_mut().zip(self.weights) {
+            *sum = if weight != 0.0 {
+                *sum / weight
+            } else {
+                Color::default()
+            };
+        }
+        self.sums
+    }
+}
+
+pub struct FilmTile {
+    width: usize,
+    top: usize,
+    bottom: usize,
+    filter: Filter,
+    radius: f64,
+    sums: Vec<Color>,
+    weights: Vec<f64>,
+}
+
+impl FilmTile {
+    /// Splats a sample taken at continuous film position `(x, y)` into
+    /// every pixel whose center lies within the filter radius
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Color) {
+        let x0 = ((x - 0.5 - self.radius).ceil() as isize).max(0);
+        let x1 = ((x - 0.5 + self.radius).floor() as isize).min(self.width as isize - 1);
+        let y0 = ((y - 0.5 - self.radius).ceil() as isize).max(self.top as isize);
+        let y1 = ((y - 0.5 + self.radius).floor() as isize).min(self.bottom as isize - 1);
+
+        for j in y0..=y1 {
+            for i in x0..=x1 {
+                let dx = i as f64 + 0.5 - x;
+                let dy = j as f64 + 0.5 - y;
+                let weight = self.filter.evaluate(dx, dy, self.radius);
+                let k = (j as usize - self.top) * self.width + i as usize;
+                self.sums[k] += color * weight;
+                self.weights[k] += weight;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::sampling;
+
+    /// Splats one jittered sample of `color` per pixel, like the renderer
+    fn render(filter: Filter, radius: f64, color: Color) -> Vec<Color> {
+        let (width, height) = (24, 16);
+        let mut film = Film::new(width, height, filter, radius);
+        let mut tile = film.tile(0..height);
+        for j in 0..height {
+            for i in 0..width {
+                let mut rng = sampling::pixel_rng(7, j * width + i);
+                let x = i as f64 + rng.gen::<f64>();
+                let y = j as f64 + rng.gen::<f64>();
+                tile.add_sample(x, y, color);
+            }
+        }
+        film.merge(tile);
+        film.into_pixels()
+    }
+
+    #[test]
+    fn every_filter_keeps_a_constant_color() {
+        let color = Color::new(0.25, 0.5, 1.0);
+        for name in ["box", "tent", "gaussian", "mitchell", "lanczos"] {
+            let filter = name.parse::<Filter>().unwrap();
+            for radius in [filter.min_radius(), 2.0, 3.5] {
+                for (k, pixel) in render(filter, radius, color).into_iter().enumerate() {
+                    let error = (pixel.r - color.r)
+                        .abs()
+                        .max((pixel.g - color.g).abs())
+                        .max((pixel.b - color.b).abs());
+                    assert!(
+                        error < 1E-9,
+                        "{name} at radius {radius} gives {pixel:?} in pixel {k}"
+                    );
+                }
+            }
+        }
+    }
+}
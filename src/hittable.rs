@@ -0,0 +1,63 @@
+use std::fmt;
+
+use crate::{
+    bvh::Aabb,
+    vector::{Normal3, Point3},
+    Material, Ray,
+};
+
+/// Where a ray meets a surface, everything shading needs to know about it
+#[derive(Clone, Copy, Debug)]
+pub struct HitRecord<'a> {
+    /// Distance along the ray, in units of its direction
+    pub t: f64,
+    pub point: Point3,
+    /// Unit normal of the surface itself, turned to face the incoming ray
+    pub geometric_normal: Normal3,
+    /// Unit normal to shade with, on the same side as the geometric one
+    pub shading_normal: Normal3,
+    /// Weights of the triangle corners at `point`, other surfaces put all
+    /// the weight on the first
+    pub barycentric: [f64; 3],
+    /// Surface coordinates, both within `[0, 1]`
+    pub uv: (f64, f64),
+    /// Whether the ray arrived from outside, against the outward normal
+    pub front_face: bool,
+    /// Position of the shape among the scene primitives
+    pub id: usize,
+    pub material: &'a Material,
+}
+
+impl<'a> HitRecord<'a> {
+    /// Record for a hit `t` along `ray`, with both normals taken from
+    /// `outward_normal` and flipped towards the ray when it hits the back
+    pub fn new(ray: &Ray, t: f64, outward_normal: Normal3, material: &'a Material) -> Self {
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        let normal = if front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+        Self {
+            t,
+            point: ray.origin + ray.direction * t,
+            geometric_normal: normal,
+            shading_normal: normal,
+            barycentric: [1.0, 0.0, 0.0],
+            uv: (0.0, 0.0),
+            front_face,
+            id: 0,
+            material,
+        }
+    }
+}
+
+/// Geometry the renderer can place in its acceleration structure and shade
+pub trait Hittable: fmt::Debug + Send + Sync {
+    /// Closest intersection with `t` strictly between `t_min` and `t_max`,
+    /// the scene overwrites the `id` of the record
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
+
+    /// Box enclosing the whole surface
+    fn bounds(&self) -> Aabb;
+}
@@ -0,0 +1,177 @@
+use std::{f64::consts::PI, str::FromStr};
+
+use rand::Rng;
+
+use crate::{
+    bvh::Bvh,
+    light::{Light, Lighting},
+    material::{refract, schlick},
+    sampling,
+    vector::{Color, Vec3},
+    HitRecord, Ray, SHADOW_BIAS,
+};
+
+/// Bounces after which paths become candidates for Russian roulette
+const MIN_BOUNCES: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Integrator {
+    /// Direct lighting with deterministic mirror and glass bounces
+    Whitted,
+    /// Unidirectional Monte Carlo path tracing with global illumination
+    Path,
+}
+
+impl Integrator {
+    pub(crate) fn radiance(
+        &self,
+        ray: &Ray,
+        bvh: &Bvh,
+        lighting: &Lighting,
+        max_depth: usize,
+        rng: &mut impl Rng,
+    ) -> Color {
+        match self {
+            Integrator::Whitted => ray.pierce(bvh, lighting, max_depth, rng),
+            Integrator::Path => trace(ray, bvh, lighting, max_depth, rng),
+        }
+    }
+}
+
+impl FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "whitted" => Ok(Integrator::Whitted),
+            "path" => Ok(Integrator::Path),
+            _ => Err(format!(
+                "unknown integrator {name:?}, expected whitted or path"
+            )),
+        }
+    }
+}
+
+/// Follows a single path, picking one lobe of the material at every vertex
+/// with probability equal to its weight, so that on average it matches the
+/// Whitted split while also gathering diffuse interreflections. Diffuse
+/// vertices sample every light and emissive shape directly and also count
+/// them when the bounce happens to cross them, weighting both with the power
+/// heuristic
+fn trace(ray: &Ray, bvh: &Bvh, lighting: &Lighting, max_depth: usize, rng: &mut impl Rng) -> Color {
+    let mut radiance = Color::default();
+    let mut throughput = Color::splat(1.0);
+    let mut ray = ray.clone();
+    // density of the last diffuse bounce, None after the camera or a mirror
+    let mut bounce_pdf = None;
+
+    for bounce in 0..=max_depth {
+        let hit = bvh.hit(&ray);
+
+        // the light's share when the bounce could also have sampled it
+        let weight = |light: &Light, distance: f64| {
+            bounce_pdf.map_or(1.0, |pdf| {
+                let length = ray.direction.length();
+                let direction = ray.direction / length;
+                let light_pdf =
+                    light.pdf(ray.origin, direction, distance * length) * lighting.samples as f64;
+                sampling::power_heuristic(pdf, light_pdf)
+            })
+        };
+
+        let distance = hit.as_ref().map_or(f64::INFINITY, |record| record.t);
+        radiance += throughput * lighting.crossed(&ray, distance, weight);
+
+        let Some(record) = hit else {
+            break;
+        };
+
+        let hit = record.point;
+        let material = record.material;
+        let normal = record.shading_normal;
+        if material.emission != Color::default() {
+            let weight = bounce_pdf.map_or(1.0, |pdf| {
+                let light_pdf = lighting.emitters.pdf(ray.origin, &record);
+                sampling::power_heuristic(pdf, light_pdf * lighting.samples as f64)
+            });
+            radiance += throughput * material.emission * weight;
+        }
+        let eta = if record.front_face {
+            1.0 / material.ior
+        } else {
+            material.ior
+        };
+
+        let lobe = rng.gen::<f64>();
+        let direction = if lobe < material.reflectivity {
+            bounce_pdf = None;
+            ray.direction - normal * 2.0 * ray.direction.dot(normal)
+        } else if lobe < material.reflectivity + material.transmissivity {
+            bounce_pdf = None;
+            let cos = -ray.direction.dot(normal);
+            match refract(ray.direction, normal, eta) {
+                Some(direction) if rng.gen::<f64>() >= schlick(cos, eta) => direction,
+                _ => ray.direction - normal * 2.0 * ray.direction.dot(normal),
+            }
+        } else {
+            let outgoing = -ray.direction;
+            radiance += throughput * direct(&record, outgoing, bvh, lighting, rng);
+
+            let direction = sampling::cosine_hemisphere(normal, rng);
+            let cos = normal.dot(direction);
+            let pdf = cos / PI;
+            if pdf <= 0.0 {
+                break;
+            }
+            throughput = throughput * material.brdf(&record, direction, outgoing) * cos / pdf;
+            bounce_pdf = Some(pdf);
+            direction
+        };
+
+        if bounce >= MIN_BOUNCES {
+            let survival = throughput.max_element().min(0.95);
+            if rng.gen::<f64>() >= survival {
+                break;
+            }
+            throughput /= survival;
+        }
+
+        let offset = record.geometric_normal * SHADOW_BIAS;
+        let origin = if direction.dot(record.geometric_normal) > 0.0 {
+            hit + offset
+        } else {
+            hit - offset
+        };
+        ray = Ray::new(origin, direction);
+    }
+
+    radiance
+}
+
+/// Light arriving straight from the light sources and reflected towards
+/// `outgoing`. A light without area can only be reached this way so it
+/// takes the full weight, the others share it with the bounce
+fn direct(
+    record: &HitRecord,
+    outgoing: Vec3,
+    bvh: &Bvh,
+    lighting: &Lighting,
+    rng: &mut impl Rng,
+) -> Color {
+    let (hit, normal, material) = (record.point, record.shading_normal, record.material);
+    let mut radiance = Color::default();
+    lighting.illuminate(hit, rng, |illumination, samples| {
+        let incoming = illumination.direction;
+        let cos = normal.dot(incoming);
+        if cos <= 0.0 || bvh.occluded(hit, incoming, illumination.distance) {
+            return;
+        }
+        let weight = illumination.pdf.map_or(1.0, |pdf| {
+            sampling::power_heuristic(pdf * samples as f64, cos / PI)
+        });
+        radiance +=
+            material.brdf(record, incoming, outgoing) * illumination.irradiance * cos * weight
+                / samples as f64;
+    });
+    radiance
+}
@@ -0,0 +1,44 @@
+//! A small ray tracer: load a [`Scene`], hand it to a [`Renderer`] and save
+//! the resulting [`Image`]
+
+mod bvh;
+mod camera;
+mod emitter;
+mod environment;
+mod film;
+mod hittable;
+mod integrator;
+mod light;
+mod material;
+mod mesh;
+pub mod obj;
+mod png;
+mod ray;
+mod render;
+mod sampling;
+mod scene;
+mod shape;
+mod sky;
+mod texture;
+mod transform;
+mod vector;
+
+pub use bvh::Aabb;
+pub use camera::Camera;
+pub use environment::Environment;
+pub use film::Filter;
+pub use hittable::{HitRecord, Hittable};
+pub use integrator::Integrator;
+pub use light::Light;
+pub use material::Material;
+pub use ray::Ray;
+pub use render::{Image, Renderer};
+pub use scene::{Scene, SceneError, Settings, View};
+pub use sky::Sky;
+pub use texture::Texture;
+pub use vector::{Color, Normal3, Point3, Vec3};
+
+pub(crate) use shape::{Shape, Sphere, Triangle};
+
+const TOLERANCE: f64 = 1E-10;
+const SHADOW_BIAS: f64 = 1E-6;
@@ -0,0 +1,406 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use rand::Rng;
+
+use crate::{
+    emitter::Emitters,
+    environment::Environment,
+    sampling,
+    shape::Sphere,
+    vector::{Color, Normal3, Point3, Vec3},
+    Hittable, Material, Ray, TOLERANCE,
+};
+
+/// Lights with an area glow wherever rays cross them, from the camera or
+/// from a bounce, but are not part of the geometry: they cast no shadows
+/// and leave what lies behind them in view. An emissive shape is the way to
+/// get a solid lamp
+#[derive(PartialEq, Clone, Debug)]
+pub enum Light {
+    /// Shines `intensity` evenly in every direction, falling off with the
+    /// squared distance
+    Point {
+        origin: Point3,
+        intensity: f64,
+        color: Color,
+    },
+    /// Parallel rays travelling along `direction` from infinitely far away,
+    /// `intensity` is the irradiance they bring to a surface facing them
+    Directional {
+        direction: Vec3,
+        intensity: f64,
+        color: Color,
+    },
+    /// A point light confined to a cone around `direction`, fading out
+    /// smoothly from `inner_angle` to `angle` degrees off its axis
+    Spot {
+        origin: Point3,
+        direction: Vec3,
+        intensity: f64,
+        color: Color,
+        angle: f64,
+        inner_angle: f64,
+    },
+    /// A glowing ball that lights distant surfaces like a point light of the
+    /// same `intensity` at its center
+    Sphere {
+        center: Point3,
+        radius: f64,
+        intensity: f64,
+        color: Color,
+    },
+    /// The parallelogram spanned by `u` and `v` from `corner`, glowing on the
+    /// side `u × v` points to. `intensity` is what it sends straight out,
+    /// as for a point light
+    Rectangle {
+        corner: Point3,
+        u: Vec3,
+        v: Vec3,
+        intensity: f64,
+        color: Color,
+    },
+    /// A disk glowing on the side `normal` points to, `intensity` is what
+    /// it sends straight out, as for a point light
+    Disk {
+        center: Point3,
+        normal: Normal3,
+        radius: f64,
+        intensity: f64,
+        color: Color,
+    },
+    /// Light from every direction infinitely far away, which is also what
+    /// rays leaving the scene see
+    Environment(Arc<Environment>),
+}
+
+/// Light reaching a point straight from a light, ignoring anything in between
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Illumination {
+    /// Unit vector from the point towards the light
+    pub(crate) direction: Vec3,
+    /// Infinite for directional lights
+    pub(crate) distance: f64,
+    /// Irradiance on a surface facing the light, estimated from this one
+    /// direction for lights with an area
+    pub(crate) irradiance: Color,
+    /// Solid angle density `direction` was drawn with, `None` for lights
+    /// without area, which can only be reached this way
+    pub(crate) pdf: Option<f64>,
+}
+
+impl Light {
+    /// Whether the light has no area, so it can only be reached by sampling
+    /// it directly
+    pub(crate) fn is_delta(&self) -> bool {
+        matches!(
+            self,
+            Light::Point { .. } | Light::Directional { .. } | Light::Spot { .. }
+        )
+    }
+
+    /// Light arriving at `point`, lights with an area send it from a point
+    /// drawn at random on the part of their surface visible from `point`
+    pub(crate) fn illuminate(&self, point: Point3, rng: &mut impl Rng) -> Option<Illumination> {
+        match *self {
+            Light::Point {
+                origin,
+                intensity,
+                color,
+            } => {
+                let to_light = origin - point;
+                let distance = to_light.length();
+                Some(Illumination {
+                    direction: to_light / distance,
+                    distance,
+                    irradiance: color * intensity / (distance * distance),
+                    pdf: None,
+                })
+            }
+            Light::Directional {
+                direction,
+                intensity,
+                color,
+            } => Some(Illumination {
+                direction: -direction.unit(),
+                distance: f64::INFINITY,
+                irradiance: color * intensity,
+                pdf: None,
+            }),
+            Light::Spot {
+                origin,
+                direction,
+                intensity,
+                color,
+                angle,
+                inner_angle,
+            } => {
+                let to_light = origin - point;
+                let distance = to_light.length();
+                let cos = -to_light.dot(direction.unit()) / distance;
+                let falloff = smoothstep(
+                    angle.to_radians().cos(),
+                    inner_angle.to_radians().cos(),
+                    cos,
+                );
+                (falloff > 0.0).then(|| Illumination {
+                    direction: to_light / distance,
+                    distance,
+                    irradiance: color * intensity * falloff / (distance * distance),
+                    pdf: None,
+                })
+            }
+            Light::Sphere { center, .. } => {
+                let cos_max = self.cos_max(point)?;
+                let axis = (center - point).unit();
+                let direction = sampling::uniform_cone(axis, cos_max, rng);
+                let distance = self.hit(&Ray::new(point, direction))?;
+                let pdf = 1.0 / (2.0 * PI * (1.0 - cos_max));
+                Some(Illumination {
+                    direction,
+                    distance,
+                    irradiance: self.radiance() / pdf,
+                    pdf: Some(pdf),
+                })
+            }
+            Light::Rectangle { corner, u, v, .. } => {
+                let target = corner + u * rng.gen::<f64>() + v * rng.gen::<f64>();
+                self.illuminate_from(point, target)
+            }
+            Light::Environment(ref environment) => {
+                let (direction, pdf) = environment.sample(rng)?;
+                Some(Illumination {
+                    direction,
+                    distance: f64::INFINITY,
+                    irradiance: environment.radiance(direction) / pdf,
+                    pdf: Some(pdf),
+                })
+            }
+            Light::Disk {
+                center,
+                normal,
+                radius,
+                ..
+            } => {
+                let disk = sampling::unit_disk(rng) * radius;
+                let (tangent, bitangent) = sampling::basis(normal.into());
+                let target = center + tangent * disk.x + bitangent * disk.y;
+                self.illuminate_from(point, target)
+            }
+        }
+    }
+
+    /// Light sent from `target` on a flat light towards `point`, with the
+    /// density of having picked `target` uniformly over the light's area
+    fn illuminate_from(&self, point: Point3, target: Point3) -> Option<Illumination> {
+        let to_light = target - point;
+        let distance = to_light.length();
+        let direction = to_light / distance;
+        let pdf = self.pdf(point, direction, distance);
+        (pdf > 0.0).then(|| Illumination {
+            direction,
+            distance,
+            irradiance: self.radiance() / pdf,
+            pdf: Some(pdf),
+        })
+    }
+
+    /// Radiance reaching the origin of a ray travelling along `direction`
+    /// that hits the light, flat lights are dark from behind
+    pub(crate) fn emitted(&self, direction: Vec3) -> Color {
+        if let Light::Environment(environment) = self {
+            return environment.radiance(direction.unit());
+        }
+        match self.normal() {
+            Some(normal) if direction.dot(normal) >= 0.0 => Color::default(),
+            _ => self.radiance(),
+        }
+    }
+
+    /// Radiance leaving any point of the surface of the light
+    fn radiance(&self) -> Color {
+        match *self {
+            Light::Sphere {
+                intensity, color, ..
+            }
+            | Light::Rectangle {
+                intensity, color, ..
+            }
+            | Light::Disk {
+                intensity, color, ..
+            } => color * intensity / self.area(),
+            _ => Color::default(),
+        }
+    }
+
+    /// Area of a flat light, or of a sphere light's silhouette
+    fn area(&self) -> f64 {
+        match *self {
+            Light::Sphere { radius, .. } | Light::Disk { radius, .. } => PI * radius * radius,
+            Light::Rectangle { u, v, .. } => u.cross(v).length(),
+            _ => 0.0,
+        }
+    }
+
+    /// Unit normal on the glowing side of a flat light
+    fn normal(&self) -> Option<Normal3> {
+        match *self {
+            Light::Rectangle { u, v, .. } => Some(Normal3::unit(u.cross(v))),
+            Light::Disk { normal, .. } => Some(normal),
+            _ => None,
+        }
+    }
+
+    /// Distance along `ray` to the surface of a light with an area,
+    /// infinite for the environment
+    pub(crate) fn hit(&self, ray: &Ray) -> Option<f64> {
+        match *self {
+            Light::Environment(_) => Some(f64::INFINITY),
+            Light::Sphere { center, radius, .. } => Sphere::new(0, center, radius, &LIGHT_MATERIAL)
+                .intersect(ray, TOLERANCE, f64::INFINITY)
+                .map(|record| record.t),
+            Light::Rectangle { corner, u, v, .. } => {
+                let n = u.cross(v);
+                let t = plane_hit(ray, corner, n)?;
+                // coordinates of the hit along u and v, which need not be
+                // perpendicular
+                let q = ray.origin + ray.direction * t - corner;
+                let w = n / n.length_squared();
+                let a = w.dot(q.cross(v));
+                let b = w.dot(u.cross(q));
+                ((0.0..=1.0).contains(&a) && (0.0..=1.0).contains(&b)).then_some(t)
+            }
+            Light::Disk {
+                center,
+                normal,
+                radius,
+                ..
+            } => {
+                let t = plane_hit(ray, center, normal.into())?;
+                let offset = ray.origin + ray.direction * t - center;
+                (offset.length_squared() <= radius * radius).then_some(t)
+            }
+            _ => None,
+        }
+    }
+
+    /// Cosine of the half angle of the cone a sphere light subtends from
+    /// `point`, `None` when `point` is inside the light
+    fn cos_max(&self, point: Point3) -> Option<f64> {
+        let Light::Sphere { center, radius, .. } = *self else {
+            return None;
+        };
+        let sin2_max = radius * radius / (center - point).length_squared();
+        (sin2_max < 1.0).then(|| (1.0 - sin2_max).sqrt())
+    }
+
+    /// Solid angle density with which `illuminate` picks the unit
+    /// `direction` from `point`, which meets the light `distance` away
+    pub(crate) fn pdf(&self, point: Point3, direction: Vec3, distance: f64) -> f64 {
+        match self {
+            Light::Sphere { .. } => self
+                .cos_max(point)
+                .map_or(0.0, |cos_max| 1.0 / (2.0 * PI * (1.0 - cos_max))),
+            Light::Rectangle { .. } | Light::Disk { .. } => {
+                let Some(normal) = self.normal() else {
+                    return 0.0;
+                };
+                let cos = -direction.dot(normal);
+                if cos > 0.0 {
+                    distance * distance / (self.area() * cos)
+                } else {
+                    0.0
+                }
+            }
+            Light::Environment(environment) => environment.pdf(direction),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Distance along `ray` to the plane through `origin` perpendicular to
+/// `normal`
+fn plane_hit(ray: &Ray, origin: Point3, normal: Vec3) -> Option<f64> {
+    let denominator = ray.direction.dot(normal);
+    if denominator.abs() < TOLERANCE {
+        return None;
+    }
+    let t = (origin - ray.origin).dot(normal) / denominator;
+    (t > TOLERANCE).then_some(t)
+}
+
+/// Everything that lights a scene, the lights it lists and its emissive shapes
+#[derive(Debug)]
+pub(crate) struct Lighting<'a> {
+    pub(crate) lights: &'a [Light],
+    pub(crate) emitters: Emitters<'a>,
+    /// Samples per shading point of each light with an area, and of the
+    /// emissive shapes all together
+    pub(crate) samples: usize,
+}
+
+impl Lighting<'_> {
+    /// Radiance from the lights that `ray` crosses before reaching
+    /// `distance` along it, infinite when it leaves the scene, each scaled by
+    /// `weight` given the light and where the ray meets it. Lights block
+    /// nothing so the ray carries on past them
+    pub(crate) fn crossed(
+        &self,
+        ray: &Ray,
+        distance: f64,
+        mut weight: impl FnMut(&Light, f64) -> f64,
+    ) -> Color {
+        self.lights
+            .iter()
+            .filter_map(|light| {
+                let t = light.hit(ray).filter(|&t| t <= distance)?;
+                Some(light.emitted(ray.direction) * weight(light, t))
+            })
+            .sum()
+    }
+
+    /// Hands `shade` every sample of light arriving straight at `point`,
+    /// along with the number of samples drawn from the same source
+    pub(crate) fn illuminate(
+        &self,
+        point: Point3,
+        rng: &mut impl Rng,
+        mut shade: impl FnMut(&Illumination, usize),
+    ) {
+        for light in self.lights {
+            let samples = if light.is_delta() { 1 } else { self.samples };
+            for _ in 0..samples {
+                if let Some(illumination) = light.illuminate(point, rng) {
+                    shade(&illumination, samples);
+                }
+            }
+        }
+        if !self.emitters.is_empty() {
+            for _ in 0..self.samples {
+                if let Some(illumination) = self.emitters.illuminate(point, rng) {
+                    shade(&illumination, self.samples);
+                }
+            }
+        }
+    }
+}
+
+/// Hermite interpolation from zero at `edge0` to one at `edge1`
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    if edge0 == edge1 {
+        return if x >= edge1 { 1.0 } else { 0.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Spheres need a material, lights are shaded through `Light::radiance`
+static LIGHT_MATERIAL: Material = Material {
+    diffuse: Color::splat(0.0),
+    specular: Color::splat(0.0),
+    brightness: 0.0,
+    reflectivity: 0.0,
+    transmissivity: 0.0,
+    ior: 1.0,
+    emission: Color::splat(0.0),
+    texture: None,
+};
@@ -0,0 +1,59 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    texture::Texture,
+    vector::{Color, Normal3, Vec3},
+    HitRecord,
+};
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Material {
+    pub diffuse: Color,
+    pub specular: Color,
+    pub brightness: f64,
+    pub reflectivity: f64,
+    pub transmissivity: f64,
+    pub ior: f64,
+    /// Radiance the surface gives off by itself
+    pub emission: Color,
+    /// Modulates `diffuse` across the surface
+    pub texture: Option<Arc<Texture>>,
+}
+
+impl Material {
+    /// Diffuse reflectance at surface coordinates `uv`
+    pub(crate) fn albedo(&self, uv: (f64, f64)) -> Color {
+        match &self.texture {
+            Some(texture) => self.diffuse * texture.sample(uv),
+            None => self.diffuse,
+        }
+    }
+
+    /// Lambertian diffuse plus a Phong lobe around the mirror direction,
+    /// with `incoming` pointing to the light and `outgoing` to the viewer
+    pub(crate) fn brdf(&self, record: &HitRecord, incoming: Vec3, outgoing: Vec3) -> Color {
+        let normal = record.shading_normal;
+        let mirror = normal * 2.0 * normal.dot(incoming) - incoming;
+        let r_dot_v = mirror.dot(outgoing).max(0.0);
+        self.albedo(record.uv) / PI + self.specular * r_dot_v.powf(self.brightness)
+    }
+}
+
+/// Refracted direction for a unit `direction` crossing a surface whose
+/// `normal` faces it, `eta` being the ratio of refractive indices, or `None`
+/// on total internal reflection
+pub(crate) fn refract(direction: Vec3, normal: Normal3, eta: f64) -> Option<Vec3> {
+    let cos_i = -direction.dot(normal);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(direction * eta + normal * (eta * cos_i - cos_t))
+}
+
+/// Schlick's approximation of the Fresnel reflectance
+pub(crate) fn schlick(cos: f64, eta: f64) -> f64 {
+    let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
+    r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+}
@@ -0,0 +1,35 @@
+use crate::vector::{Normal3, Point3};
+
+/// Triangles sharing their vertex data, already placed in the scene
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Mesh {
+    pub(crate) positions: Vec<Point3>,
+    /// Unit normals for smooth shading
+    pub(crate) normals: Vec<Normal3>,
+    pub(crate) texcoords: Vec<(f64, f64)>,
+    pub(crate) faces: Vec<MeshFace>,
+}
+
+/// Indices into the vertex data of a `Mesh`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct MeshFace {
+    pub(crate) positions: [u32; 3],
+    pub(crate) normals: Option<[u32; 3]>,
+    pub(crate) texcoords: Option<[u32; 3]>,
+    /// Into the scene materials
+    pub(crate) material: u32,
+}
+
+impl Mesh {
+    /// Adds a flat, untextured triangle
+    pub(crate) fn push(&mut self, points: [Point3; 3], material: usize) {
+        let first = self.positions.len() as u32;
+        self.positions.extend(points);
+        self.faces.push(MeshFace {
+            positions: [first, first + 1, first + 2],
+            normals: None,
+            texcoords: None,
+            material: material as u32,
+        });
+    }
+}
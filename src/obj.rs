@@ -1,165 +1,734 @@
 use std::{
-    f64::consts::PI,
-    fs::File,
-    io::{BufRead, BufReader},
-    str::FromStr,
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
-use crate::{vector::Vector, Material, Shape, Triangle};
+use crate::{
+    mesh::{Mesh, MeshFace},
+    transform::{
+        mul, scaling, transform_normal, transform_point, translation, Matrix4x4, IDENTITY,
+    },
+    vector::{Color, Normal3, Point3, Vec3},
+    Material, Texture,
+};
 
-pub fn read<'a>(path: &'_ str, material: &'a Material) -> Vec<Shape<'a>> {
-    let mut positions = vec![];
+/// The contents of a Wavefront OBJ file, with every face split into triangles
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Model {
+    pub positions: Vec<Point3>,
+    pub texcoords: Vec<(f64, f64)>,
+    pub normals: Vec<Normal3>,
+    pub faces: Vec<Face>,
+    /// Names given by `o` and `g` statements, faces before the first one
+    /// belong to no group
+    pub groups: Vec<String>,
+    /// Names given by `usemtl` statements
+    pub materials: Vec<String>,
+    /// Materials defined in the files named by `mtllib` statements, leaving
+    /// out the files that do not exist
+    pub library: HashMap<String, Material>,
+}
 
-    let mut min = [f64::MAX, f64::MAX, f64::MAX];
-    let mut max = [f64::MIN, f64::MIN, f64::MIN];
-    let file = || {
-        let file = File::open(path).unwrap();
-        BufReader::new(file)
-    };
+/// A triangle, corners in the order they appear in the file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Face {
+    pub vertices: [Vertex; 3],
+    /// Index into `Model::groups`
+    pub group: Option<usize>,
+    /// Index into `Model::materials`
+    pub material: Option<usize>,
+}
+
+/// Zero based indices into the attribute lists of a `Model`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vertex {
+    pub position: usize,
+    pub texcoord: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+/// An OBJ file that could not be read, with the line at fault when known
+#[derive(Debug)]
+pub struct ObjError {
+    pub(crate) path: PathBuf,
+    pub(crate) line: Option<usize>,
+    pub(crate) message: String,
+}
+
+impl ObjError {
+    /// File that failed to load, the OBJ file or material library at fault
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// One based line of the statement at fault, if the file could be read
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// What went wrong, without the location
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
 
-    for line in file().lines() {
-        let line = line.unwrap();
+pub fn load(path: &Path) -> Result<Model, ObjError> {
+    let source = fs::read_to_string(path).map_err(|e| ObjError {
+        path: path.to_path_buf(),
+        line: None,
+        message: e.to_string(),
+    })?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+    parse(&source, directory).map_err(|(line, message)| ObjError {
+        path: path.to_path_buf(),
+        line: Some(line),
+        message,
+    })
+}
+
+/// Statements the renderer has no use for, such as smoothing groups, lines
+/// and free-form geometry, are skipped. Material libraries are read from
+/// `directory`
+fn parse(source: &str, directory: &Path) -> Result<Model, (usize, String)> {
+    let mut model = Model::default();
+    let mut group = None;
+    let mut material = None;
 
-        if !line.starts_with('v') {
+    for (line, statement) in statements(source) {
+        let mut words = statement.split_whitespace();
+        let Some(keyword) = words.next() else {
             continue;
+        };
+        let arguments = words.collect::<Vec<_>>();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = numbers::<3>(&arguments).map_err(|e| (line, e))?;
+                model.positions.push(Point3::new(x, y, z));
+            }
+            "vt" => {
+                // a one dimensional coordinate leaves v at zero
+                let [u, v] = match arguments[..] {
+                    [_] => numbers::<1>(&arguments).map(|[u]| [u, 0.0]),
+                    _ => numbers::<2>(&arguments),
+                }
+                .map_err(|e| (line, e))?;
+                model.texcoords.push((u, v));
+            }
+            "vn" => {
+                let [x, y, z] = numbers::<3>(&arguments).map_err(|e| (line, e))?;
+                model.normals.push(Normal3::unit(Vec3::new(x, y, z)));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err((line, "a face needs at least three vertices".to_string()));
+                }
+                let vertices = arguments
+                    .iter()
+                    .map(|word| vertex(&model, word))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| (line, e))?;
+                let points = vertices
+                    .iter()
+                    .map(|vertex| model.positions[vertex.position])
+                    .collect::<Vec<_>>();
+                for [a, b, c] in triangulate(&points) {
+                    model.faces.push(Face {
+                        vertices: [vertices[a], vertices[b], vertices[c]],
+                        group,
+                        material,
+                    });
+                }
+            }
+            "o" | "g" => {
+                group = Some(model.groups.len());
+                model.groups.push(arguments.join(" "));
+            }
+            "usemtl" => {
+                let [name] = arguments[..] else {
+                    return Err((line, "usemtl takes a single material name".to_string()));
+                };
+                let index = model.materials.iter().position(|m| m == name);
+                material = Some(index.unwrap_or_else(|| {
+                    model.materials.push(name.to_string());
+                    model.materials.len() - 1
+                }));
+            }
+            "mtllib" => {
+                for name in arguments {
+                    // libraries often go missing when models are passed
+                    // around, their faces fall back to the mesh material
+                    let path = directory.join(name);
+                    if !path.exists() {
+                        continue;
+                    }
+                    let library = load_library(&path).map_err(|e| (line, e.to_string()))?;
+                    model.library.extend(library);
+                }
+            }
+            _ => {}
         }
+    }
 
-        let position = parse::<f64>(line);
-        debug_assert!(position.len() == 3);
+    Ok(model)
+}
 
-        for i in 0..3 {
-            if position[i] < min[i] {
-                min[i] = position[i];
+/// Materials defined in the MTL file at `path`
+fn load_library(path: &Path) -> Result<HashMap<String, Material>, ObjError> {
+    let source = fs::read_to_string(path).map_err(|e| ObjError {
+        path: path.to_path_buf(),
+        line: None,
+        message: e.to_string(),
+    })?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+    parse_library(&source, directory).map_err(|(line, message)| ObjError {
+        path: path.to_path_buf(),
+        line: Some(line),
+        message,
+    })
+}
+
+/// Converts MTL parameters into materials: `Kd`, `Ks` and `Ke` give the
+/// diffuse, specular and emitted colors, `Ns` the Phong exponent, `Ni` the
+/// refractive index, `d` or `Tr` how much light passes through and `map_Kd`
+/// a diffuse texture read from `directory`. Anything else is skipped
+fn parse_library(
+    source: &str,
+    directory: &Path,
+) -> Result<HashMap<String, Material>, (usize, String)> {
+    let mut library = HashMap::new();
+    let mut current: Option<(String, Material)> = None;
+    let mut textures: HashMap<PathBuf, Arc<Texture>> = HashMap::new();
+
+    for (line, statement) in statements(source) {
+        let mut words = statement.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let arguments = words.collect::<Vec<_>>();
+
+        if keyword == "newmtl" {
+            let [name] = arguments[..] else {
+                return Err((line, "newmtl takes a single material name".to_string()));
+            };
+            library.extend(current.take());
+            current = Some((
+                name.to_string(),
+                Material {
+                    diffuse: Color::splat(0.8),
+                    specular: Color::default(),
+                    brightness: 0.0,
+                    reflectivity: 0.0,
+                    transmissivity: 0.0,
+                    ior: 1.0,
+                    emission: Color::default(),
+                    texture: None,
+                },
+            ));
+            continue;
+        }
+        let Some((_, material)) = &mut current else {
+            continue;
+        };
+
+        let error = |e| (line, e);
+        match keyword {
+            "Kd" => material.diffuse = color(&arguments).map_err(error)?,
+            "Ks" => material.specular = color(&arguments).map_err(error)?,
+            "Ke" => material.emission = color(&arguments).map_err(error)?,
+            "Ns" => [material.brightness] = numbers(&arguments).map_err(error)?,
+            "Ni" => [material.ior] = numbers(&arguments).map_err(error)?,
+            // options such as -halo come before the value
+            "d" => {
+                let [dissolve] =
+                    numbers(&arguments[arguments.len().saturating_sub(1)..]).map_err(error)?;
+                material.transmissivity = 1.0 - dissolve;
             }
-            if position[i] > max[i] {
-                max[i] = position[i];
+            "Tr" => [material.transmissivity] = numbers(&arguments).map_err(error)?,
+            // options such as -s or -o come before the file name
+            "map_Kd" => {
+                let Some(name) = arguments.last() else {
+                    return Err((line, "map_Kd needs a file name".to_string()));
+                };
+                let path = directory.join(name);
+                let texture = match textures.get(&path) {
+                    Some(texture) => texture.clone(),
+                    None => {
+                        let texture = Texture::load(&path)
+                            .map_err(|e| (line, format!("{}: {e}", path.display())))?;
+                        let texture = Arc::new(texture);
+                        textures.insert(path, texture.clone());
+                        texture
+                    }
+                };
+                material.texture = Some(texture);
             }
+            _ => {}
         }
+    }
+    library.extend(current);
+
+    Ok(library)
+}
 
-        positions.push(position);
+/// An RGB color, a single value stands for a grey
+fn color(arguments: &[&str]) -> Result<Color, String> {
+    if arguments
+        .first()
+        .is_some_and(|word| word.parse::<f64>().is_err())
+    {
+        return Err("only RGB colors are supported".to_string());
     }
+    match arguments[..] {
+        [_] => numbers(arguments).map(|[grey]| Color::splat(grey)),
+        _ => numbers(arguments).map(Color::from),
+    }
+}
 
-    debug_assert!(positions.len() > 1);
+/// Logical lines with comments removed and continuations joined, each
+/// numbered after the physical line it starts on
+fn statements(source: &str) -> Vec<(usize, String)> {
+    let mut statements = vec![];
+    let mut pending: Option<(usize, String)> = None;
 
-    let c = min
+    for (index, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let (line, continues) = match line.trim_end().strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+
+        let (number, mut statement) = pending.take().unwrap_or((index + 1, String::new()));
+        statement.push(' ');
+        statement.push_str(line);
+        if continues {
+            pending = Some((number, statement));
+        } else {
+            statements.push((number, statement));
+        }
+    }
+    statements.extend(pending);
+
+    statements
+}
+
+/// The first `N` arguments as numbers, any extra ones such as a `w`
+/// coordinate or vertex colors are ignored
+fn numbers<const N: usize>(arguments: &[&str]) -> Result<[f64; N], String> {
+    if arguments.len() < N {
+        return Err(format!("expected {N} numbers"));
+    }
+    let mut numbers = [0.0; N];
+    for (number, word) in numbers.iter_mut().zip(arguments) {
+        *number = word
+            .parse()
+            .map_err(|_| format!("invalid number {word:?}"))?;
+    }
+    Ok(numbers)
+}
+
+/// A face corner written as `v`, `v/vt`, `v//vn` or `v/vt/vn`
+fn vertex(model: &Model, word: &str) -> Result<Vertex, String> {
+    let mut parts = word.split('/');
+    let position = parts.next().unwrap_or_default();
+    let texcoord = parts.next().filter(|part| !part.is_empty());
+    let normal = parts.next().filter(|part| !part.is_empty());
+    if parts.next().is_some() {
+        return Err(format!("invalid vertex {word:?}"));
+    }
+
+    Ok(Vertex {
+        position: index(position, model.positions.len(), "position")?,
+        texcoord: texcoord
+            .map(|i| index(i, model.texcoords.len(), "texture coordinate"))
+            .transpose()?,
+        normal: normal
+            .map(|i| index(i, model.normals.len(), "normal"))
+            .transpose()?,
+    })
+}
+
+/// Resolves a one based index, or a negative one counting back from the
+/// last element read so far, to a zero based one
+fn index(word: &str, count: usize, kind: &str) -> Result<usize, String> {
+    let index = word
+        .parse::<isize>()
+        .map_err(|_| format!("invalid {kind} index {word:?}"))?;
+    let resolved = match index {
+        1.. => index - 1,
+        ..=-1 => count as isize + index,
+        0 => return Err(format!("{kind} indices start at 1")),
+    };
+    if resolved < 0 || resolved as usize >= count {
+        return Err(format!(
+            "{kind} index {index} out of range, {count} defined so far"
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+/// Splits a polygon into triangles by clipping ears in the plane it faces,
+/// which also copes with concave polygons, falling back to a fan when the
+/// polygon is too degenerate to have any ear
+fn triangulate(points: &[Point3]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    let fan = || (1..n - 1).map(|i| [0, i, i + 1]).collect();
+    if n == 3 {
+        return fan();
+    }
+
+    // Newell's method, which tolerates slightly non planar polygons
+    let mut normal = Vec3::default();
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        normal += Vec3::new(
+            (a.y - b.y) * (a.z + b.z),
+            (a.z - b.z) * (a.x + b.x),
+            (a.x - b.x) * (a.y + b.y),
+        );
+    }
+    if normal.length_squared() == 0.0 {
+        return fan();
+    }
+
+    let turn = |a: usize, b: usize, c: usize| {
+        (points[b] - points[a])
+            .cross(points[c] - points[a])
+            .dot(normal)
+    };
+    let inside = |p: usize, [a, b, c]: [usize; 3]| {
+        turn(a, b, p) >= 0.0 && turn(b, c, p) >= 0.0 && turn(c, a, p) >= 0.0
+    };
+
+    let mut remaining = (0..n).collect::<Vec<_>>();
+    let mut triangles = Vec::with_capacity(n - 2);
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let corner = |i: usize| {
+            [
+                remaining[(i + m - 1) % m],
+                remaining[i],
+                remaining[(i + 1) % m],
+            ]
+        };
+        let ear = (0..m).find(|&i| {
+            let [a, b, c] = corner(i);
+            turn(a, b, c) > 0.0
+                && remaining
+                    .iter()
+                    .filter(|&&p| p != a && p != b && p != c)
+                    .all(|&p| !inside(p, [a, b, c]))
+        });
+        let Some(i) = ear else {
+            return fan();
+        };
+        triangles.push(corner(i));
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    triangles
+}
+
+/// Moves `model` to the origin and scales it so its longest side is one
+pub(crate) fn normalization(model: &Model) -> Matrix4x4 {
+    let (min, max) = model.positions.iter().fold(
+        (Point3::splat(f64::MAX), Point3::splat(f64::MIN)),
+        |(min, max), &p| (min.min(p), max.max(p)),
+    );
+    let size = (max - min).max_element();
+    if model.positions.is_empty() || size <= 0.0 {
+        return IDENTITY;
+    }
+
+    let center = min.lerp(max, 0.5);
+    mul(
+        translation(Point3::default() - center),
+        scaling(Vec3::splat(1.0 / size)),
+    )
+}
+
+/// `model` placed by `transform`, `material` gives the scene material of
+/// each face. Corners without a normal in the file are smoothed across
+/// edges sharper than `crease_angle` degrees, zero leaves them flat
+pub(crate) fn mesh(
+    model: &Model,
+    material: impl Fn(&Face) -> usize,
+    transform: &Matrix4x4,
+    crease_angle: f64,
+) -> Mesh {
+    let mut mesh = Mesh {
+        positions: model
+            .positions
+            .iter()
+            .map(|&p| transform_point(transform, p))
+            .collect(),
+        normals: model
+            .normals
+            .iter()
+            .map(|&n| transform_normal(transform, n))
+            .collect(),
+        texcoords: model.texcoords.clone(),
+        faces: Vec::with_capacity(model.faces.len()),
+    };
+
+    // smoothed normals are shared by every corner they come out equal for
+    let mut shared = HashMap::new();
+    let smoothed = smooth_normals(&mesh.positions, &model.faces, crease_angle);
+    for (face, smoothed) in model.faces.iter().zip(smoothed) {
+        let mut normals = [None; 3];
+        for (i, vertex) in face.vertices.iter().enumerate() {
+            normals[i] = match (vertex.normal, smoothed[i]) {
+                (Some(index), _) => Some(index as u32),
+                (None, Some(normal)) => {
+                    let key = [normal.x, normal.y, normal.z].map(f64::to_bits);
+                    Some(*shared.entry(key).or_insert_with(|| {
+                        mesh.normals.push(normal);
+                        mesh.normals.len() as u32 - 1
+                    }))
+                }
+                (None, None) => None,
+            };
+        }
+
+        mesh.faces.push(MeshFace {
+            positions: face.vertices.map(|vertex| vertex.position as u32),
+            normals: all(normals),
+            texcoords: all(face
+                .vertices
+                .map(|vertex| vertex.texcoord.map(|i| i as u32))),
+            material: material(face) as u32,
+        });
+    }
+
+    mesh
+}
+
+/// All three indices, or none when some corner lacks one
+fn all(indices: [Option<u32>; 3]) -> Option<[u32; 3]> {
+    match indices {
+        [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+        _ => None,
+    }
+}
+
+/// Normal at every face corner averaged over the faces around its position
+/// whose normals are within `crease_angle` degrees of the face's own, each
+/// weighted by its area and by its angle at that position
+fn smooth_normals(
+    positions: &[Point3],
+    faces: &[Face],
+    crease_angle: f64,
+) -> Vec<[Option<Normal3>; 3]> {
+    if crease_angle <= 0.0 {
+        return vec![[None; 3]; faces.len()];
+    }
+    let cos_crease = crease_angle.to_radians().cos();
+
+    let corners = |face: &Face| face.vertices.map(|vertex| positions[vertex.position]);
+    // twice the area along the unit normal
+    let areas = faces
+        .iter()
+        .map(|face| {
+            let [a, b, c] = corners(face);
+            (b - a).cross(c - a)
+        })
+        .collect::<Vec<_>>();
+    let angles = faces
         .iter()
-        .zip(max.iter())
-        .map(|(min, max)| (max + min) / 2.0)
+        .map(|face| {
+            let points = corners(face);
+            [0, 1, 2].map(|i| {
+                let u = points[(i + 1) % 3] - points[i];
+                let v = points[(i + 2) % 3] - points[i];
+                u.cross(v).length().atan2(u.dot(v))
+            })
+        })
         .collect::<Vec<_>>();
 
-    let d = min
+    let mut around = vec![vec![]; positions.len()];
+    for (k, face) in faces.iter().enumerate() {
+        for (i, vertex) in face.vertices.iter().enumerate() {
+            around[vertex.position].push((k, i));
+        }
+    }
+
+    faces
         .iter()
-        .zip(max.iter())
-        .map(|(min, max)| max - min)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
+        .enumerate()
+        .map(|(k, face)| {
+            let normal = areas[k].unit();
+            face.vertices.map(|vertex| {
+                let sum = around[vertex.position]
+                    .iter()
+                    .filter(|&&(other, _)| areas[other].unit().dot(normal) >= cos_crease)
+                    .fold(Vec3::default(), |sum, &(other, i)| {
+                        sum + areas[other] * angles[other][i]
+                    });
+                (sum.length_squared() > 0.0).then(|| Normal3::unit(sum))
+            })
+        })
+        .collect()
+}
 
-    debug_assert!(d > 0.0);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let positions = positions
-        .into_iter()
-        .map(|p| (0..3).map(|i| (p[i] - c[i]) / d).collect::<Vec<_>>())
-        .collect::<Vec<_>>();
+    fn parse_str(source: &str) -> Result<Model, (usize, String)> {
+        parse(source, Path::new(""))
+    }
 
-    let m = get_transform_matrix();
-    let mut shapes = vec![];
-    for line in file().lines() {
-        let line = line.unwrap();
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
 
-        if !line.starts_with('f') {
-            continue;
-        }
+    #[test]
+    fn negative_indices_count_back_from_the_last_vertex() {
+        let model = parse_str(&format!("{TRIANGLE}f -3 -2 -1\nv 1 1 0\nf -4 -1 -2\n")).unwrap();
+        let positions = |face: &Face| face.vertices.map(|vertex| vertex.position);
+        assert_eq!(positions(&model.faces[0]), [0, 1, 2]);
+        assert_eq!(positions(&model.faces[1]), [0, 3, 2]);
+    }
 
-        let indices = parse::<usize>(line);
-        debug_assert!(indices.len() == 3);
+    #[test]
+    fn vertices_may_carry_texture_coordinates_and_normals() {
+        let source = format!(
+            "{TRIANGLE}vt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\n\
+             f 1//1 2//1 3//1\nf 1/1/1 2/2/1 3/3/1\nf 1/3 2/2 3/1\n"
+        );
+        let model = parse_str(&source).unwrap();
+        let [a, b, c] = [0, 1, 2].map(|k| model.faces[k].vertices[1]);
+        assert_eq!(
+            a,
+            Vertex {
+                position: 1,
+                texcoord: None,
+                normal: Some(0)
+            }
+        );
+        assert_eq!(
+            b,
+            Vertex {
+                position: 1,
+                texcoord: Some(1),
+                normal: Some(0)
+            }
+        );
+        assert_eq!(
+            c,
+            Vertex {
+                position: 1,
+                texcoord: Some(1),
+                normal: None
+            }
+        );
+    }
 
-        let points = indices
-            .into_iter()
-            .map(|i| {
-                let p = &positions[i - 1];
+    #[test]
+    fn bad_indices_report_their_line() {
+        assert_eq!(parse_str(&format!("{TRIANGLE}f 1 2 0\n")).unwrap_err().0, 4);
+        assert_eq!(parse_str(&format!("{TRIANGLE}f 1 2 4\n")).unwrap_err().0, 4);
+        assert_eq!(
+            parse_str(&format!("{TRIANGLE}f 1 2 -4\n")).unwrap_err().0,
+            4
+        );
+        assert_eq!(
+            parse_str(&format!("{TRIANGLE}f 1//2 2 3\n")).unwrap_err().0,
+            4
+        );
+        assert_eq!(
+            parse_str(&format!("{TRIANGLE}f 1/2/3/4 2 3\n"))
+                .unwrap_err()
+                .0,
+            4
+        );
+    }
 
-                let a = p[0] * m[0][0] + p[1] * m[1][0] + p[2] * m[2][0] + m[3][0];
-                let b = p[0] * m[0][1] + p[1] * m[1][1] + p[2] * m[2][1] + m[3][1];
-                let c = p[0] * m[0][2] + p[1] * m[1][2] + p[2] * m[2][2] + m[3][2];
-                let w = p[0] * m[0][3] + p[1] * m[1][3] + p[2] * m[2][3] + m[3][3];
-                let coords = vec![a, b, c].into_iter().map(|c| c / w).collect();
-                Vector::new(coords)
-            })
-            .collect::<Vec<_>>();
-
-        shapes.push(Shape::Triangle(Triangle::new(points, material)));
-    }
-
-    shapes
-}
-
-fn parse<T>(mut line: String) -> Vec<T>
-where
-    T: FromStr,
-    <T as FromStr>::Err: std::fmt::Debug,
-{
-    // the first char is either "v" or "f"
-    line.split_off(1)
-        .split_whitespace()
-        .map(|n| n.trim().parse::<T>().unwrap())
-        .collect::<Vec<T>>()
-}
-
-fn get_transform_matrix() -> [[f64; 4]; 4] {
-    let a2 = PI / 2.0 - 0.5;
-    let a6 = PI / 6.0;
-
-    let n_cos_a6 = (-a6).cos();
-    let n_cos_a2 = (-a2).cos();
-    let p_cos_a2 = (a2).cos();
-    let n_sin_a6 = (-a6).sin();
-    let n_sin_a2 = (-a2).sin();
-    let p_sin_a2 = (a2).sin();
-
-    let a = [
-        [1.0, 0.0, 0.0, 0.0],
-        [0.0, n_cos_a2, n_sin_a2, 0.0],
-        [0.0, -n_sin_a2, n_cos_a2, 0.0],
-        [0.0, 0.0, 0.0, 1.0],
-    ];
-    let b = [
-        [p_cos_a2, 0.0, -p_sin_a2, 0.0],
-        [0.0, 1.0, 0.0, 0.0],
-        [p_sin_a2, 0.0, p_cos_a2, 0.0],
-        [0.0, 0.0, 0.0, 1.0],
-    ];
-    let c = [
-        [1.0, 0.0, 0.0, 0.0],
-        [0.0, n_cos_a6, n_sin_a6, 0.0],
-        [0.0, -n_sin_a6, n_cos_a6, 0.0],
-        [0.0, 0.0, 0.0, 1.0],
-    ];
-
-    let d = [
-        [15.0, 0.0, 0.0, 0.0],
-        [0.0, 15.0, 0.0, 0.0],
-        [0.0, 0.0, 15.0, 0.0],
-        [0.0, 0.5, 10.0, 1.0],
-    ];
-
-    mul(mul(mul(a, b), c), d)
-}
-
-type Matrix4x4 = [[f64; 4]; 4];
-
-fn mul(a: Matrix4x4, b: Matrix4x4) -> Matrix4x4 {
-    let mut m = [
-        [0.0, 0.0, 0.0, 0.0],
-        [0.0, 0.0, 0.0, 0.0],
-        [0.0, 0.0, 0.0, 0.0],
-        [0.0, 0.0, 0.0, 0.0],
-    ];
-
-    for i in 0..4 {
-        for j in 0..4 {
-            m[i][j] = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j] + a[i][3] * b[3][j];
+    #[test]
+    fn continuations_join_lines_and_keep_the_first_line_number() {
+        let model =
+            parse_str("v 0 0 \\\n 0 # origin\nv 1 0 0\nv 0 1 0\nf 1 \\\n2 \\\n3\n").unwrap();
+        assert_eq!(model.positions[0], Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(model.faces.len(), 1);
+
+        let (line, _) = parse_str("v 0 0 0\nf 1 \\\n2 \\\n3\n").unwrap_err();
+        assert_eq!(line, 2);
+    }
+
+    #[test]
+    fn missing_libraries_are_skipped() {
+        let source = format!("mtllib missing.mtl\nusemtl red\n{TRIANGLE}f 1 2 3\n");
+        let model = parse_str(&source).unwrap();
+        assert!(model.library.is_empty());
+        assert_eq!(model.faces[0].material, Some(0));
+    }
+
+    #[test]
+    fn polygons_are_split_into_triangles() {
+        let model = parse_str(&format!("{TRIANGLE}v 1 1 0\nf 1 2 4 3\n")).unwrap();
+        assert_eq!(model.faces.len(), 2);
+    }
+
+    #[test]
+    fn concave_polygons_are_clipped_into_triangles_facing_the_same_way() {
+        // a square with a notch cut into its top edge
+        let points = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(4.0, 0.0, 0.0),
+            Point3::new(4.0, 4.0, 0.0),
+            Point3::new(2.0, 1.0, 0.0),
+            Point3::new(0.0, 4.0, 0.0),
+        ];
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), 3);
+
+        let mut area = 0.0;
+        for [a, b, c] in triangles {
+            let normal = (points[b] - points[a]).cross(points[c] - points[a]);
+            assert!(normal.z > 0.0, "triangle {:?} is flipped", [a, b, c]);
+            area += normal.z / 2.0;
         }
+        assert!((area - 10.0).abs() < 1E-12);
     }
 
-    m
+    #[test]
+    fn libraries_turn_parameters_into_materials() {
+        let source = "Kd 0 0 0\n\
+                      newmtl red\nKd 1 0 0\nKs 0.5\nNs 10\nd 0.25\n\
+                      newmtl lamp\nKe 1 1 1\nNi 1.5\nTr 0.1\nillum 2\n";
+        let library = parse_library(source, Path::new("")).unwrap();
+        assert_eq!(library.len(), 2);
+
+        let red = &library["red"];
+        assert_eq!(red.diffuse, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(red.specular, Color::splat(0.5));
+        assert_eq!(red.brightness, 10.0);
+        assert_eq!(red.transmissivity, 0.75);
+
+        let lamp = &library["lamp"];
+        assert_eq!(lamp.diffuse, Color::splat(0.8));
+        assert_eq!(lamp.emission, Color::splat(1.0));
+        assert_eq!(lamp.ior, 1.5);
+        assert_eq!(lamp.transmissivity, 0.1);
+    }
+
+    #[test]
+    fn library_errors_report_their_line() {
+        let source = "newmtl a\nKd spectral file.rfl\n";
+        assert_eq!(parse_library(source, Path::new("")).unwrap_err().0, 2);
+        let source = "newmtl a\nKd 1 0 0\nnewmtl\n";
+        assert_eq!(parse_library(source, Path::new("")).unwrap_err().0, 3);
+    }
 }
@@ -1,17 +1,14 @@
-use crate::vector::Vector;
-use image::{ImageBuffer, Rgb, RgbImage};
+use crate::vector::Color;
+use image::{ImageBuffer, ImageResult, Rgb, RgbImage};
+use std::path::Path;
 
-pub fn write(path: &str, width: u32, height: u32, pixels: &[Vector]) {
+pub fn write(path: &Path, width: u32, height: u32, pixels: &[Color]) -> ImageResult<()> {
     let mut buffer: RgbImage = ImageBuffer::new(width, height);
 
     for ((_, _, dst_pixel), src_pixel) in buffer.enumerate_pixels_mut().zip(pixels.iter()) {
-        let p = src_pixel
-            .coords
-            .iter()
-            .map(|c| c.max(0.0).min(1.0) * 255.0)
-            .collect::<Vec<_>>();
-        *dst_pixel = Rgb([p[0] as u8, p[1] as u8, p[2] as u8]);
+        let p = src_pixel.map(|c| c.clamp(0.0, 1.0) * 255.0);
+        *dst_pixel = Rgb([p.r as u8, p.g as u8, p.b as u8]);
     }
 
-    buffer.save(path).unwrap();
+    buffer.save(path)
 }
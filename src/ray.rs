@@ -0,0 +1,111 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::{
+    bvh::Bvh,
+    light::{Illumination, Lighting},
+    material::{refract, schlick},
+    vector::{Color, Point3, Vec3},
+    HitRecord, SHADOW_BIAS,
+};
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Ray {
+    pub origin: Point3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Point3, direction: Vec3) -> Self {
+        Self { origin, direction }
+    }
+
+    /// Whitted style: local shading plus mirror reflection and refraction,
+    /// followed recursively for up to `depth` more bounces. Lights with an
+    /// area are averaged over several points for soft shadows
+    pub(crate) fn pierce(
+        &self,
+        bvh: &Bvh,
+        lighting: &Lighting,
+        depth: usize,
+        rng: &mut impl Rng,
+    ) -> Color {
+        let record = bvh.hit(self);
+        let distance = record.as_ref().map_or(f64::INFINITY, |record| record.t);
+        let lights = lighting.crossed(self, distance, |_, _| 1.0);
+        let Some(record) = record else {
+            return lights;
+        };
+
+        let hit = record.point;
+        let material = record.material;
+        let mut pixel = lights + material.emission;
+        let local = (1.0 - material.reflectivity - material.transmissivity).max(0.0);
+
+        if local > 0.0 {
+            lighting.illuminate(hit, rng, |illumination, samples| {
+                if !bvh.occluded(hit, illumination.direction, illumination.distance) {
+                    pixel += phong(&record, self.origin, illumination) * local / samples as f64;
+                }
+            });
+        }
+
+        if depth == 0 || (material.reflectivity <= 0.0 && material.transmissivity <= 0.0) {
+            return pixel;
+        }
+
+        let normal = record.shading_normal;
+        let offset = record.geometric_normal * SHADOW_BIAS;
+        let eta = if record.front_face {
+            1.0 / material.ior
+        } else {
+            material.ior
+        };
+
+        let mut reflected = material.reflectivity;
+        if material.transmissivity > 0.0 {
+            match refract(self.direction, normal, eta) {
+                Some(direction) => {
+                    let cos = -self.direction.dot(normal);
+                    let fresnel = schlick(cos, eta);
+                    reflected += material.transmissivity * fresnel;
+
+                    let ray = Ray::new(hit - offset, direction);
+                    let refracted = material.transmissivity * (1.0 - fresnel);
+                    pixel += ray.pierce(bvh, lighting, depth - 1, rng) * refracted;
+                }
+                // total internal reflection
+                None => reflected += material.transmissivity,
+            }
+        }
+
+        if reflected > 0.0 {
+            let direction = self.direction - normal * 2.0 * self.direction.dot(normal);
+            let ray = Ray::new(hit + offset, direction);
+            pixel += ray.pierce(bvh, lighting, depth - 1, rng) * reflected;
+        }
+
+        pixel
+    }
+}
+
+/// Lambert plus Phong highlights seen from `eye` under the light of a single
+/// source, the local term of the Whitted integrator
+fn phong(record: &HitRecord, eye: Point3, illumination: &Illumination) -> Color {
+    let material = record.material;
+    let normal = record.shading_normal;
+    let sl = illumination.direction;
+    let l = illumination.irradiance;
+
+    let n_dot_l = normal.dot(sl);
+    let n_dot_l_floor = n_dot_l.max(0.0);
+    let brdf = l * (material.albedo(record.uv) / PI) * n_dot_l_floor;
+    let r = ((normal * n_dot_l - sl) * 2.0).unit();
+
+    let v = (eye - record.point).unit();
+    let r_dot_v = r.dot(v).max(0.0);
+
+    let m = r_dot_v.powf(material.brightness) * n_dot_l_floor;
+    brdf + l * material.specular * m
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use image::ImageResult;
+use indicatif::ProgressBar;
+use rand::Rng;
+use rayon::prelude::*;
+
+use crate::{
+    bvh::Bvh, emitter::Emitters, film::Film, light::Lighting, png, sampling, vector::Color, Scene,
+    Settings,
+};
+
+/// Image rows rendered together into one film tile
+const BAND_HEIGHT: usize = 16;
+
+/// Renders scenes on the global rayon thread pool
+#[derive(Clone, Debug, Default)]
+pub struct Renderer {
+    /// Draw a progress bar on the terminal, one tick per image row
+    pub progress: bool,
+}
+
+impl Renderer {
+    pub fn render(&self, scene: &Scene) -> Image {
+        let shapes = scene.shapes();
+        let emitters = Emitters::new(&shapes);
+        let bvh = Bvh::new(shapes);
+        let camera = scene.camera();
+        let Settings {
+            width: n,
+            height: m,
+            spp,
+            seed,
+            filter,
+            filter_radius,
+            integrator,
+            max_depth,
+            light_samples,
+        } = scene.settings;
+        let lighting = Lighting {
+            lights: &scene.lights,
+            emitters,
+            samples: light_samples,
+        };
+        let image_width = n as f64;
+        let image_height = m as f64;
+
+        let mut film = Film::new(n, m, filter, filter_radius);
+        let bar = if self.progress {
+            ProgressBar::new(m as u64)
+        } else {
+            ProgressBar::hidden()
+        };
+
+        // bands are rendered a batch at a time and merged in order, which
+        // keeps only a few tiles alive and the sums independent of scheduling
+        let bands = (0..m)
+            .step_by(BAND_HEIGHT)
+            .map(|top| top..(top + BAND_HEIGHT).min(m))
+            .collect::<Vec<_>>();
+        for batch in bands.chunks(4 * rayon::current_num_threads()) {
+            let tiles = batch
+                .par_iter()
+                .map(|rows| {
+                    let mut tile = film.tile(rows.clone());
+                    for j in rows.clone() {
+                        for i in 0..n {
+                            let mut rng = sampling::pixel_rng(seed, j * n + i);
+
+                            for _ in 0..spp {
+                                let x = i as f64 + rng.gen::<f64>();
+                                let y = j as f64 + rng.gen::<f64>();
+
+                                let ray = camera.ray(x / image_width, y / image_height, &mut rng);
+                                let color =
+                                    integrator.radiance(&ray, &bvh, &lighting, max_depth, &mut rng);
+                                tile.add_sample(x, y, color);
+                            }
+                        }
+                        bar.inc(1);
+                    }
+                    tile
+                })
+                .collect::<Vec<_>>();
+
+            for tile in tiles {
+                film.merge(tile);
+            }
+        }
+        bar.finish();
+
+        Image {
+            width: n,
+            height: m,
+            pixels: film.into_pixels(),
+        }
+    }
+}
+
+/// Linear radiance per pixel, row by row from the top left corner
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    /// Writes the image clamped to `[0, 1]`, the format follows the extension
+    pub fn save(&self, path: &Path) -> ImageResult<()> {
+        png::write(path, self.width as u32, self.height as u32, &self.pixels)
+    }
+}
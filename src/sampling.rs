@@ -0,0 +1,165 @@
+use std::f64::consts::{FRAC_PI_4, PI};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use crate::vector::{Normal3, Vec3};
+
+/// Uniform point on the unit disk in the z = 0 plane, using Shirley's
+/// concentric mapping so that nearby samples stay nearby
+pub fn unit_disk(rng: &mut impl Rng) -> Vec3 {
+    let a = 2.0 * rng.gen::<f64>() - 1.0;
+    let b = 2.0 * rng.gen::<f64>() - 1.0;
+
+    if a == 0.0 && b == 0.0 {
+        return Vec3::default();
+    }
+
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, 2.0 * FRAC_PI_4 - FRAC_PI_4 * (a / b))
+    };
+
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+/// Direction around `normal` with density `cos(theta) / PI`, obtained by
+/// projecting a disk sample up onto the hemisphere
+pub fn cosine_hemisphere(normal: Normal3, rng: &mut impl Rng) -> Vec3 {
+    let d = unit_disk(rng);
+    let z = (1.0 - d.x * d.x - d.y * d.y).max(0.0).sqrt();
+    let (tangent, bitangent) = basis(normal.into());
+    tangent * d.x + bitangent * d.y + normal * z
+}
+
+/// Direction drawn uniformly from the cone around `axis` whose half angle
+/// has cosine `cos_max`, with density `1 / (2 * PI * (1 - cos_max))`
+pub fn uniform_cone(axis: Vec3, cos_max: f64, rng: &mut impl Rng) -> Vec3 {
+    let cos = 1.0 - rng.gen::<f64>() * (1.0 - cos_max);
+    let sin = (1.0 - cos * cos).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.gen::<f64>();
+    let (tangent, bitangent) = basis(axis);
+    tangent * (sin * phi.cos()) + bitangent * (sin * phi.sin()) + axis * cos
+}
+
+/// Direction drawn uniformly over the unit sphere, with density `1 / (4 * PI)`
+pub fn uniform_sphere(rng: &mut impl Rng) -> Vec3 {
+    let z = 1.0 - 2.0 * rng.gen::<f64>();
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.gen::<f64>();
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Veach's power heuristic with an exponent of two, the weight of a sample
+/// drawn with density `pdf` when `other_pdf` could also have produced it
+pub fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
+
+/// Two unit vectors completing the unit vector `axis` into an orthonormal basis,
+/// following Duff et al. "Building an Orthonormal Basis, Revisited"
+pub fn basis(axis: Vec3) -> (Vec3, Vec3) {
+    let sign = 1.0_f64.copysign(axis.z);
+    let a = -1.0 / (sign + axis.z);
+    let b = axis.x * axis.y * a;
+    let tangent = Vec3::new(1.0 + sign * axis.x * axis.x * a, sign * b, -sign * axis.x);
+    let bitangent = Vec3::new(b, sign + axis.y * axis.y * a, -axis.y);
+    (tangent, bitangent)
+}
+
+/// Piecewise constant density over `[0, 1)`, each of the equally wide bins
+/// getting a share proportional to its weight
+#[derive(Clone, Debug, PartialEq)]
+pub struct Distribution {
+    weights: Vec<f64>,
+    /// Running total of the weights, starting at zero
+    cdf: Vec<f64>,
+}
+
+impl Distribution {
+    /// All zero weights fall back to a uniform density
+    pub fn new(mut weights: Vec<f64>) -> Self {
+        if weights.iter().sum::<f64>() <= 0.0 {
+            weights.fill(1.0);
+        }
+        let mut cdf = Vec::with_capacity(weights.len() + 1);
+        cdf.push(0.0);
+        for weight in &weights {
+            cdf.push(cdf[cdf.len() - 1] + weight);
+        }
+        Self { weights, cdf }
+    }
+
+    /// Sum of the weights
+    pub fn total(&self) -> f64 {
+        self.cdf[self.weights.len()]
+    }
+
+    /// Bin and position in `[0, 1)` that `u` in `[0, 1)` maps to, together
+    /// with the density there
+    pub fn sample(&self, u: f64) -> (usize, f64, f64) {
+        let target = u * self.total();
+        let bin = (self.cdf.partition_point(|&sum| sum <= target) - 1).min(self.weights.len() - 1);
+        let offset = (target - self.cdf[bin]) / self.weights[bin];
+        let x = ((bin as f64 + offset.clamp(0.0, 1.0)) / self.weights.len() as f64)
+            .min(1.0 - f64::EPSILON);
+        (bin, x, self.pdf(bin))
+    }
+
+    /// Density over `[0, 1)` inside `bin`
+    pub fn pdf(&self, bin: usize) -> f64 {
+        self.weights[bin] * self.weights.len() as f64 / self.total()
+    }
+}
+
+/// Generator for one pixel, seeded from its index alone so renders come out
+/// the same no matter how rayon schedules the pixels across threads
+pub fn pixel_rng(seed: u64, index: usize) -> SmallRng {
+    SmallRng::seed_from_u64(seed ^ (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distribution_density_integrates_to_one() {
+        let distribution = Distribution::new(vec![1.0, 0.0, 3.0, 0.5]);
+        let integral = (0..4).map(|bin| distribution.pdf(bin)).sum::<f64>() / 4.0;
+        assert!((integral - 1.0).abs() < 1E-12);
+        assert_eq!(distribution.total(), 4.5);
+    }
+
+    #[test]
+    fn distribution_samples_invert_the_cumulative_weights() {
+        let distribution = Distribution::new(vec![1.0, 0.0, 3.0]);
+
+        let (bin, x, pdf) = distribution.sample(0.125);
+        assert_eq!(bin, 0);
+        assert!((x - 1.0 / 6.0).abs() < 1E-12);
+        assert_eq!(pdf, 0.75);
+
+        let (bin, x, pdf) = distribution.sample(0.625);
+        assert_eq!(bin, 2);
+        assert!((x - 5.0 / 6.0).abs() < 1E-12);
+        assert_eq!(pdf, 2.25);
+
+        // empty bins are never picked, even at their boundary
+        assert_eq!(distribution.sample(0.25).0, 2);
+        assert!(distribution.sample(1.0 - f64::EPSILON).1 < 1.0);
+    }
+
+    #[test]
+    fn distribution_without_weight_is_uniform() {
+        let distribution = Distribution::new(vec![0.0; 4]);
+        assert_eq!(distribution.pdf(3), 1.0);
+        let (bin, x, _) = distribution.sample(0.6);
+        assert_eq!(bin, 2);
+        assert!((x - 0.6).abs() < 1E-12);
+    }
+}
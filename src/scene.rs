@@ -0,0 +1,1019 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    bvh::Aabb,
+    camera::Camera,
+    film::Filter,
+    integrator::Integrator,
+    mesh::Mesh,
+    obj::{self, Face, Model, ObjError},
+    transform::{
+        mul, rotation_x, rotation_y, rotation_z, scaling, transform_point, translation, Matrix4x4,
+        IDENTITY,
+    },
+    vector::{Color, Normal3, Point3, Vec3},
+    Environment, Hittable, Light, Material, Shape, Sky, Sphere, Texture, Triangle,
+};
+
+/// How the image is sampled and reconstructed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub width: usize,
+    pub height: usize,
+    pub spp: usize,
+    pub seed: u64,
+    pub filter: Filter,
+    /// In pixels, at least `Filter::min_radius`
+    pub filter_radius: f64,
+    pub integrator: Integrator,
+    pub max_depth: usize,
+    /// Shadow rays towards each light with an area per shading point
+    pub light_samples: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            width: 720,
+            height: 405,
+            spp: 16,
+            seed: 0,
+            filter: Filter::MitchellNetravali {
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            },
+            filter_radius: 2.0,
+            integrator: Integrator::Whitted,
+            max_depth: 5,
+            light_samples: 1,
+        }
+    }
+}
+
+/// Where the camera stands and what it looks at, the aspect ratio comes
+/// from the image so it is only known once the settings are final
+#[derive(Clone, Debug, PartialEq)]
+pub struct View {
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub up: Vec3,
+    pub vfov: f64,
+    pub aperture: f64,
+    pub focus_distance: f64,
+}
+
+#[derive(Debug)]
+enum Object {
+    Sphere {
+        center: Point3,
+        radius: f64,
+        material: usize,
+    },
+    Mesh(Mesh),
+}
+
+#[derive(Debug)]
+pub struct Scene {
+    pub settings: Settings,
+    pub view: View,
+    pub lights: Vec<Light>,
+    materials: Vec<Material>,
+    objects: Vec<Object>,
+    hittables: Vec<Box<dyn Hittable>>,
+}
+
+impl Scene {
+    /// A scene with nothing in it yet, `materials` are referred to by their
+    /// position when adding geometry
+    pub fn new(
+        settings: Settings,
+        view: View,
+        lights: Vec<Light>,
+        materials: Vec<Material>,
+    ) -> Scene {
+        Scene {
+            settings,
+            view,
+            lights,
+            materials,
+            objects: vec![],
+            hittables: vec![],
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Scene, SceneError> {
+        let source = fs::read_to_string(path).map_err(|e| SceneError {
+            path: path.to_path_buf(),
+            line: None,
+            message: e.to_string(),
+        })?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        Scene::parse(&source, directory).map_err(|(span, message)| SceneError {
+            path: path.to_path_buf(),
+            line: span.map(|span| line(&source, span.start)),
+            message,
+        })
+    }
+
+    /// Parses a scene, resolving mesh paths against `directory`
+    fn parse(source: &str, directory: &Path) -> Result<Scene, (Option<Range<usize>>, String)> {
+        let file: SceneFile =
+            toml::from_str(source).map_err(|e| (e.span(), e.message().to_string()))?;
+
+        let render = file.render;
+        let defaults = Settings::default();
+        let filter = match &render.filter {
+            Some(filter) => parse_spanned(filter)?,
+            None => defaults.filter,
+        };
+        let settings = Settings {
+            width: positive(&render.width, "width", defaults.width)?,
+            height: positive(&render.height, "height", defaults.height)?,
+            spp: positive(&render.spp, "spp", defaults.spp)?,
+            seed: render.seed.unwrap_or(defaults.seed),
+            filter,
+            filter_radius: match &render.filter_radius {
+                Some(radius) if *radius.get_ref() < filter.min_radius() => {
+                    let message = format!(
+                        "filter_radius must be at least {} for this filter",
+                        filter.min_radius()
+                    );
+                    return Err((Some(radius.span()), message));
+                }
+                Some(radius) => *radius.get_ref(),
+                None => defaults.filter_radius.max(filter.min_radius()),
+            },
+            integrator: match &render.integrator {
+                Some(integrator) => parse_spanned(integrator)?,
+                None => defaults.integrator,
+            },
+            max_depth: render.max_depth.unwrap_or(defaults.max_depth),
+            light_samples: positive(
+                &render.light_samples,
+                "light_samples",
+                defaults.light_samples,
+            )?,
+        };
+
+        let span = file.camera.span();
+        let camera = file.camera.into_inner();
+        let look_from = Point3::from(camera.look_from);
+        let look_at = Point3::from(camera.look_at);
+        if look_from == look_at {
+            let message = "the camera must look_at a point other than look_from".to_string();
+            return Err((Some(span), message));
+        }
+        if Vec3::from(camera.up)
+            .cross(look_at - look_from)
+            .length_squared()
+            == 0.0
+        {
+            let message = "the camera up must not be parallel to where it looks".to_string();
+            return Err((Some(span), message));
+        }
+        let view = View {
+            look_from,
+            look_at,
+            up: Vec3::from(camera.up),
+            vfov: camera.vfov,
+            aperture: camera.aperture,
+            focus_distance: camera
+                .focus_distance
+                .unwrap_or_else(|| (look_at - look_from).length()),
+        };
+
+        let lights = file
+            .lights
+            .into_iter()
+            .map(|light| {
+                let span = light.span();
+                light
+                    .into_inner()
+                    .lights(directory)
+                    .map_err(|message| (Some(span), message))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .concat();
+
+        let mut names = HashMap::new();
+        let mut materials = vec![];
+        for (name, material) in file.materials {
+            let texture = match &material.texture {
+                Some(texture) => {
+                    let path = directory.join(texture.get_ref());
+                    let image = Texture::load(&path).map_err(|e| {
+                        let message = format!("{}: {e}", path.display());
+                        (Some(texture.span()), message)
+                    })?;
+                    Some(Arc::new(image))
+                }
+                None => None,
+            };
+            names.insert(name, materials.len());
+            materials.push(Material {
+                diffuse: Color::from(material.diffuse),
+                specular: Color::from(material.specular),
+                brightness: material.brightness,
+                reflectivity: material.reflectivity,
+                transmissivity: material.transmissivity,
+                ior: material.ior,
+                emission: Color::from(material.emission) * material.emission_strength,
+                texture,
+            });
+        }
+        let material = |name: &Spanned<String>| {
+            names.get(name.get_ref()).copied().ok_or_else(|| {
+                let message = format!("unknown material {:?}", name.get_ref());
+                (Some(name.span()), message)
+            })
+        };
+
+        let mut objects = vec![];
+        for sphere in &file.spheres {
+            let transform = matrix(&sphere.transform);
+            let Some(scale) = uniform_scale(&sphere.transform) else {
+                let span = sphere.transform.as_ref().map(Spanned::span);
+                return Err((span, "spheres can only be scaled uniformly".to_string()));
+            };
+            objects.push(Object::Sphere {
+                center: transform_point(&transform, Point3::from(sphere.center)),
+                radius: sphere.radius * scale,
+                material: material(&sphere.material)?,
+            });
+        }
+        let mut triangles = Mesh::default();
+        for triangle in &file.triangles {
+            let transform = matrix(&triangle.transform);
+            let points = triangle
+                .points
+                .map(|point| transform_point(&transform, Point3::from(point)));
+            triangles.push(points, material(&triangle.material)?);
+        }
+        if !triangles.faces.is_empty() {
+            objects.push(Object::Mesh(triangles));
+        }
+        for mesh in &file.meshes {
+            let path = directory.join(mesh.path.get_ref());
+            let model = obj::load(&path).map_err(|e| (Some(mesh.path.span()), e.to_string()))?;
+            let fallback = mesh.material.as_ref().map(&material).transpose()?;
+            let material = import_materials(&model, fallback, &mut materials)
+                .map_err(|message| (Some(mesh.path.span()), message))?;
+            let mut transform = matrix(&mesh.transform);
+            if mesh.normalize {
+                transform = mul(obj::normalization(&model), transform);
+            }
+            objects.push(Object::Mesh(obj::mesh(
+                &model,
+                material,
+                &transform,
+                mesh.crease_angle,
+            )));
+        }
+
+        Ok(Scene {
+            settings,
+            view,
+            lights,
+            materials,
+            objects,
+            hittables: vec![],
+        })
+    }
+
+    /// A quick look at a single mesh: default settings, a neutral material,
+    /// a camera placed from the mesh bounds and daylight
+    pub fn preview(path: &Path) -> Result<Scene, SceneError> {
+        let model = obj::load(path)?;
+        let material = Material {
+            diffuse: Color::splat(0.6),
+            specular: Color::splat(0.3),
+            brightness: 40.0,
+            reflectivity: 0.0,
+            transmissivity: 0.0,
+            ior: 1.0,
+            emission: Color::default(),
+            texture: None,
+        };
+        let mut materials = vec![material];
+        let material =
+            import_materials(&model, Some(0), &mut materials).map_err(|message| SceneError {
+                path: path.to_path_buf(),
+                line: None,
+                message,
+            })?;
+        let mesh = obj::mesh(&model, material, &IDENTITY, default_crease_angle());
+
+        let bounds = mesh
+            .positions
+            .iter()
+            .fold(Aabb::empty(), |bounds, &point| bounds.grow(point));
+        let center = bounds.centroid();
+        let size = (bounds.max - bounds.min).length();
+
+        let look_from = center + Vec3::new(0.0, 0.25, -1.0).unit() * size * 1.5;
+        let view = View {
+            look_from,
+            look_at: center,
+            up: Vec3::new(0.0, 1.0, 0.0),
+            vfov: 40.0,
+            aperture: 0.0,
+            focus_distance: (center - look_from).length(),
+        };
+        // the sun over the left shoulder of the camera
+        let sky = Sky {
+            elevation: 35.0,
+            azimuth: -135.0,
+            turbidity: 3.0,
+        };
+        let lights = vec![
+            Light::Environment(Arc::new(sky.environment(1.0))),
+            sky.sun(1.0),
+        ];
+
+        Ok(Scene {
+            settings: Settings {
+                light_samples: 4,
+                ..Settings::default()
+            },
+            view,
+            lights,
+            materials,
+            objects: vec![Object::Mesh(mesh)],
+            hittables: vec![],
+        })
+    }
+
+    pub fn camera(&self) -> Camera {
+        let view = &self.view;
+        let aspect_ratio = self.settings.width as f64 / self.settings.height as f64;
+        Camera::new(
+            view.look_from,
+            view.look_at,
+            view.up,
+            view.vfov,
+            aspect_ratio,
+            view.aperture,
+            view.focus_distance,
+        )
+    }
+
+    /// Places a sphere made of the material at position `material`
+    ///
+    /// Panics if there is no such material
+    pub fn add_sphere(&mut self, center: Point3, radius: f64, material: usize) {
+        assert!(material < self.materials.len(), "no material {material}");
+        self.objects.push(Object::Sphere {
+            center,
+            radius,
+            material,
+        });
+    }
+
+    /// Places user defined geometry in the scene next to what it was loaded with
+    pub fn add(&mut self, hittable: impl Hittable + 'static) {
+        self.hittables.push(Box::new(hittable));
+    }
+
+    /// Every primitive in the scene
+    pub(crate) fn shapes(&self) -> Vec<Shape<'_>> {
+        let mut shapes = vec![];
+        for object in &self.objects {
+            match object {
+                Object::Sphere {
+                    center,
+                    radius,
+                    material,
+                } => {
+                    let id = shapes.len();
+                    let sphere = Sphere::new(id, *center, *radius, &self.materials[*material]);
+                    shapes.push(Shape::Sphere(sphere));
+                }
+                Object::Mesh(mesh) => {
+                    for (k, face) in mesh.faces.iter().enumerate() {
+                        let material = &self.materials[face.material as usize];
+                        let triangle = Triangle::new(shapes.len(), mesh, k, material);
+                        shapes.push(Shape::Triangle(triangle));
+                    }
+                }
+            }
+        }
+        for hittable in &self.hittables {
+            shapes.push(Shape::Custom(shapes.len(), hittable.as_ref()));
+        }
+        shapes
+    }
+}
+
+/// A scene that failed to load, with the line at fault when known
+#[derive(Debug)]
+pub struct SceneError {
+    path: PathBuf,
+    line: Option<usize>,
+    message: String,
+}
+
+impl SceneError {
+    /// File that failed to load, the scene or a mesh it refers to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// One based line at fault, when the error can be pinned to one
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// What went wrong, without the location
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<ObjError> for SceneError {
+    fn from(error: ObjError) -> Self {
+        Self {
+            path: error.path,
+            line: error.line,
+            message: error.message,
+        }
+    }
+}
+
+/// Scene material of every face in `model`, adding the ones its libraries
+/// define to `materials` and using `fallback` for the rest
+fn import_materials(
+    model: &Model,
+    fallback: Option<usize>,
+    materials: &mut Vec<Material>,
+) -> Result<impl Fn(&Face) -> usize, String> {
+    if fallback.is_none() && model.faces.iter().any(|face| face.material.is_none()) {
+        return Err("the mesh has faces without a material, give it one".to_string());
+    }
+
+    let mut imported = vec![];
+    for name in &model.materials {
+        let index = match (model.library.get(name), fallback) {
+            (Some(material), _) => {
+                materials.push(material.clone());
+                materials.len() - 1
+            }
+            (None, Some(fallback)) => fallback,
+            (None, None) => {
+                return Err(format!(
+                    "material {name:?} is not in the mesh libraries, give the mesh one"
+                ))
+            }
+        };
+        imported.push(index);
+    }
+    Ok(move |face: &Face| match face.material {
+        Some(material) => imported[material],
+        None => fallback.expect("faces without a material were ruled out"),
+    })
+}
+
+fn line(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+fn parse_spanned<T>(value: &Spanned<String>) -> Result<T, (Option<Range<usize>>, String)>
+where
+    T: std::str::FromStr<Err = String>,
+{
+    value
+        .get_ref()
+        .parse()
+        .map_err(|message| (Some(value.span()), message))
+}
+
+/// `value` if it is above zero, `default` if it was left out
+fn positive(
+    value: &Option<Spanned<usize>>,
+    name: &str,
+    default: usize,
+) -> Result<usize, (Option<Range<usize>>, String)> {
+    match value {
+        Some(value) if *value.get_ref() == 0 => {
+            Err((Some(value.span()), format!("{name} must be positive")))
+        }
+        Some(value) => Ok(*value.get_ref()),
+        None => Ok(default),
+    }
+}
+
+/// Scales, then rotates about x, y and z in that order, then translates
+fn matrix(transform: &Option<Spanned<TransformFile>>) -> Matrix4x4 {
+    let Some(transform) = transform else {
+        return IDENTITY;
+    };
+    let transform = transform.get_ref();
+
+    let scale = match transform.scale {
+        Some(Scale::Uniform(factor)) => Vec3::splat(factor),
+        Some(Scale::Axes(factors)) => Vec3::from(factors),
+        None => Vec3::splat(1.0),
+    };
+    let [x, y, z] = transform.rotate.unwrap_or_default().map(f64::to_radians);
+    let offset = Vec3::from(transform.translate.unwrap_or_default());
+
+    let rotation = mul(mul(rotation_x(x), rotation_y(y)), rotation_z(z));
+    mul(mul(scaling(scale), rotation), translation(offset))
+}
+
+fn uniform_scale(transform: &Option<Spanned<TransformFile>>) -> Option<f64> {
+    let scale = transform.as_ref().and_then(|t| t.get_ref().scale.as_ref());
+    match scale {
+        None => Some(1.0),
+        Some(Scale::Uniform(factor)) => Some(factor.abs()),
+        Some(Scale::Axes([x, y, z])) if x.abs() == y.abs() && y.abs() == z.abs() => Some(x.abs()),
+        Some(Scale::Axes(_)) => None,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    #[serde(default)]
+    render: RenderFile,
+    camera: Spanned<CameraFile>,
+    #[serde(default)]
+    lights: Vec<Spanned<LightFile>>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialFile>,
+    #[serde(default)]
+    spheres: Vec<SphereFile>,
+    #[serde(default)]
+    triangles: Vec<TriangleFile>,
+    #[serde(default)]
+    meshes: Vec<MeshFile>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RenderFile {
+    width: Option<Spanned<usize>>,
+    height: Option<Spanned<usize>>,
+    spp: Option<Spanned<usize>>,
+    seed: Option<u64>,
+    filter: Option<Spanned<String>>,
+    filter_radius: Option<Spanned<f64>>,
+    integrator: Option<Spanned<String>>,
+    max_depth: Option<usize>,
+    light_samples: Option<Spanned<usize>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraFile {
+    look_from: [f64; 3],
+    look_at: [f64; 3],
+    #[serde(default = "default_up")]
+    up: [f64; 3],
+    #[serde(default = "default_vfov")]
+    vfov: f64,
+    #[serde(default)]
+    aperture: f64,
+    focus_distance: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum LightFile {
+    Point {
+        origin: [f64; 3],
+        intensity: f64,
+        #[serde(default = "default_white")]
+        color: [f64; 3],
+    },
+    Directional {
+        direction: [f64; 3],
+        intensity: f64,
+        #[serde(default = "default_white")]
+        color: [f64; 3],
+    },
+    Spot {
+        origin: [f64; 3],
+        direction: [f64; 3],
+        intensity: f64,
+        #[serde(default = "default_white")]
+        color: [f64; 3],
+        /// Half angle of the cone in degrees
+        angle: f64,
+        /// Half angle inside which the light is at full strength, the same
+        /// as `angle` for a hard edge
+        inner_angle: Option<f64>,
+    },
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        intensity: f64,
+        #[serde(default = "default_white")]
+        color: [f64; 3],
+    },
+    Rectangle {
+        corner: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        intensity: f64,
+        #[serde(default = "default_white")]
+        color: [f64; 3],
+    },
+    Disk {
+        center: [f64; 3],
+        normal: [f64; 3],
+        radius: f64,
+        intensity: f64,
+        #[serde(default = "default_white")]
+        color: [f64; 3],
+    },
+    Environment {
+        /// Equirectangular image relative to the scene file
+        path: String,
+        /// Degrees about the vertical axis
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+    /// Daylight sky, together with the sun unless `sun` is false
+    Sky {
+        /// Degrees above the horizon
+        elevation: f64,
+        /// Degrees from +z towards +x
+        #[serde(default)]
+        azimuth: f64,
+        #[serde(default = "default_turbidity")]
+        turbidity: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+        #[serde(default = "default_sun")]
+        sun: bool,
+    },
+}
+
+impl LightFile {
+    /// Resolves image paths against `directory`, a sky comes with its sun
+    fn lights(self, directory: &Path) -> Result<Vec<Light>, String> {
+        let light = match self {
+            LightFile::Point {
+                origin,
+                intensity,
+                color,
+            } => Light::Point {
+                origin: Point3::from(origin),
+                intensity,
+                color: Color::from(color),
+            },
+            LightFile::Directional {
+                direction,
+                intensity,
+                color,
+            } => Light::Directional {
+                direction: Vec3::from(direction),
+                intensity,
+                color: Color::from(color),
+            },
+            LightFile::Spot {
+                origin,
+                direction,
+                intensity,
+                color,
+                angle,
+                inner_angle,
+            } => Light::Spot {
+                origin: Point3::from(origin),
+                direction: Vec3::from(direction),
+                intensity,
+                color: Color::from(color),
+                angle,
+                inner_angle: inner_angle.unwrap_or(angle).min(angle),
+            },
+            LightFile::Sphere {
+                center,
+                radius,
+                intensity,
+                color,
+            } => Light::Sphere {
+                center: Point3::from(center),
+                radius,
+                intensity,
+                color: Color::from(color),
+            },
+            LightFile::Rectangle {
+                corner,
+                u,
+                v,
+                intensity,
+                color,
+            } => {
+                let (u, v) = (Vec3::from(u), Vec3::from(v));
+                if u.cross(v).length() <= 0.0 {
+                    return Err("rectangle light edges u and v must not be parallel".to_string());
+                }
+                Light::Rectangle {
+                    corner: Point3::from(corner),
+                    u,
+                    v,
+                    intensity,
+                    color: Color::from(color),
+                }
+            }
+            LightFile::Disk {
+                center,
+                normal,
+                radius,
+                intensity,
+                color,
+            } => {
+                if Vec3::from(normal).length() <= 0.0 {
+                    return Err("disk light normal must not be zero".to_string());
+                }
+                Light::Disk {
+                    center: Point3::from(center),
+                    normal: Normal3::unit(Vec3::from(normal)),
+                    radius,
+                    intensity,
+                    color: Color::from(color),
+                }
+            }
+            LightFile::Environment {
+                path,
+                rotation,
+                intensity,
+            } => {
+                let path = directory.join(path);
+                let environment = Environment::load(&path, rotation, intensity)
+                    .map_err(|e| format!("{}: {e}", path.display()))?;
+                Light::Environment(Arc::new(environment))
+            }
+            LightFile::Sky {
+                elevation,
+                azimuth,
+                turbidity,
+                intensity,
+                sun,
+            } => {
+                if !(2.0..=10.0).contains(&turbidity) {
+                    return Err("sky turbidity must be between 2 and 10".to_string());
+                }
+                let sky = Sky {
+                    elevation,
+                    azimuth,
+                    turbidity,
+                };
+                let mut lights = vec![Light::Environment(Arc::new(sky.environment(intensity)))];
+                if sun {
+                    lights.push(sky.sun(intensity));
+                }
+                return Ok(lights);
+            }
+        };
+        match light {
+            Light::Sphere { radius, .. } | Light::Disk { radius, .. } if radius <= 0.0 => {
+                Err("light radius must be positive".to_string())
+            }
+            light => Ok(vec![light]),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialFile {
+    diffuse: [f64; 3],
+    #[serde(default)]
+    specular: [f64; 3],
+    #[serde(default)]
+    brightness: f64,
+    #[serde(default)]
+    reflectivity: f64,
+    #[serde(default)]
+    transmissivity: f64,
+    #[serde(default = "default_ior")]
+    ior: f64,
+    #[serde(default)]
+    emission: [f64; 3],
+    /// Scales `emission`, so that its color can be given apart from how
+    /// bright it is
+    #[serde(default = "default_emission_strength")]
+    emission_strength: f64,
+    /// Image modulating the diffuse color, relative to the scene file
+    texture: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereFile {
+    center: [f64; 3],
+    radius: f64,
+    material: Spanned<String>,
+    transform: Option<Spanned<TransformFile>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriangleFile {
+    points: [[f64; 3]; 3],
+    material: Spanned<String>,
+    transform: Option<Spanned<TransformFile>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeshFile {
+    path: Spanned<String>,
+    /// For faces the mesh materials do not cover
+    material: Option<Spanned<String>>,
+    transform: Option<Spanned<TransformFile>>,
+    /// Center the mesh on the origin and scale it to fit a unit cube before
+    /// the transform
+    #[serde(default)]
+    normalize: bool,
+    /// Edges sharper than this many degrees stay faceted, zero for flat shading
+    #[serde(default = "default_crease_angle")]
+    crease_angle: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformFile {
+    translate: Option<[f64; 3]>,
+    /// Degrees about the x, y and z axes
+    rotate: Option<[f64; 3]>,
+    scale: Option<Scale>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scale {
+    Uniform(f64),
+    Axes([f64; 3]),
+}
+
+fn default_up() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_vfov() -> f64 {
+    90.0
+}
+
+fn default_white() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_ior() -> f64 {
+    1.0
+}
+
+fn default_emission_strength() -> f64 {
+    1.0
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+fn default_turbidity() -> f64 {
+    3.0
+}
+
+fn default_sun() -> bool {
+    true
+}
+
+fn default_crease_angle() -> f64 {
+    60.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMERA: &str = "[camera]\nlook_from = [0, 0, 0]\nlook_at = [0, 0, 1]\n";
+
+    fn parse_str(source: &str) -> Scene {
+        Scene::parse(source, Path::new("")).unwrap()
+    }
+
+    /// Line and message of the error loading `source`
+    fn error(source: &str) -> (usize, String) {
+        let (span, message) = Scene::parse(source, Path::new("")).unwrap_err();
+        let span = span.unwrap_or_else(|| panic!("no line for {message:?}"));
+        (line(source, span.start), message)
+    }
+
+    #[test]
+    fn settings_left_out_take_their_defaults() {
+        let scene = parse_str(CAMERA);
+        assert_eq!(scene.settings, Settings::default());
+        assert!(scene.lights.is_empty());
+        assert_eq!(scene.view.focus_distance, 1.0);
+    }
+
+    #[test]
+    fn syntax_errors_report_their_line() {
+        assert_eq!(error(&format!("{CAMERA}\n[render]\nspp = \n")).0, 6);
+        assert_eq!(error(&format!("{CAMERA}\n[render]\nssp = 4\n")).0, 6);
+    }
+
+    #[test]
+    fn counts_must_be_positive() {
+        for setting in ["width", "height", "spp", "light_samples"] {
+            let (line, message) = error(&format!("{CAMERA}[render]\nseed = 1\n{setting} = 0\n"));
+            assert_eq!(line, 6);
+            assert_eq!(message, format!("{setting} must be positive"));
+        }
+    }
+
+    #[test]
+    fn filters_need_their_minimum_radius() {
+        let source = format!("{CAMERA}[render]\nfilter = \"mitchell\"\nfilter_radius = 1.5\n");
+        assert_eq!(error(&source).0, 6);
+        let source = format!("{CAMERA}[render]\nfilter = \"box\"\nfilter_radius = 0.5\n");
+        assert_eq!(parse_str(&source).settings.filter_radius, 0.5);
+        let source = format!("{CAMERA}[render]\nfilter = \"tent\"\nfilter_radius = -1\n");
+        assert_eq!(error(&source).0, 6);
+    }
+
+    #[test]
+    fn degenerate_cameras_are_rejected() {
+        let (line, _) = error("[camera]\nlook_from = [0, 1, 0]\nlook_at = [0, 1, 0]\n");
+        assert_eq!(line, 1);
+        let (line, _) = error("\n[camera]\nlook_from = [0, 0, 0]\nlook_at = [0, 2, 0]\n");
+        assert_eq!(line, 2);
+    }
+
+    #[test]
+    fn unknown_materials_report_their_line() {
+        let source =
+            format!("{CAMERA}\n[[spheres]]\ncenter = [0, 0, 3]\nradius = 1\nmaterial = \"red\"\n");
+        assert_eq!(error(&source), (8, "unknown material \"red\"".to_string()));
+    }
+
+    #[test]
+    fn transforms_scale_then_rotate_then_translate() {
+        let source = format!(
+            "{CAMERA}[materials.grey]\ndiffuse = [0.5, 0.5, 0.5]\n\
+             [[spheres]]\ncenter = [1, 0, 0]\nradius = 1\nmaterial = \"grey\"\n\
+             transform = {{ translate = [0, 0, 5], rotate = [0, 90, 0], scale = 2 }}\n"
+        );
+        let scene = parse_str(&source);
+        let Object::Sphere { center, radius, .. } = &scene.objects[0] else {
+            panic!("expected a sphere");
+        };
+        assert!((*center - Point3::new(0.0, 0.0, 3.0)).length() < 1E-12);
+        assert_eq!(*radius, 2.0);
+    }
+
+    #[test]
+    fn spheres_can_only_be_scaled_uniformly() {
+        let source = format!(
+            "{CAMERA}[materials.grey]\ndiffuse = [0.5, 0.5, 0.5]\n\
+             [[spheres]]\ncenter = [0, 0, 3]\nradius = 1\nmaterial = \"grey\"\n\
+             transform = {{ scale = [1, 2, 1] }}\n"
+        );
+        assert_eq!(error(&source).0, 10);
+
+        let source = source.replace("[1, 2, 1]", "[2, -2, 2]");
+        let Object::Sphere { radius, .. } = &parse_str(&source).objects[0] else {
+            panic!("expected a sphere");
+        };
+        assert_eq!(*radius, 2.0);
+    }
+
+    #[test]
+    fn invalid_lights_report_their_line() {
+        let lights = [
+            "type = \"rectangle\"\ncorner = [0, 2, 0]\nu = [1, 0, 0]\nv = [2, 0, 0]\nintensity = 1",
+            "type = \"disk\"\ncenter = [0, 2, 0]\nnormal = [0, 0, 0]\nradius = 1\nintensity = 1",
+            "type = \"sphere\"\ncenter = [0, 2, 0]\nradius = 0\nintensity = 1",
+            "type = \"sky\"\nelevation = 30\nturbidity = 1",
+            "type = \"environment\"\npath = \"missing.hdr\"",
+        ];
+        for light in lights {
+            let source = format!("{CAMERA}\n[[lights]]\ntype = \"point\"\norigin = [0, 1, 0]\nintensity = 1\n\n[[lights]]\n{light}\n");
+            assert_eq!(error(&source).0, 10, "{light}");
+        }
+    }
+}
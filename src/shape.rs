@@ -0,0 +1,289 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::{
+    bvh::Aabb,
+    mesh::{Mesh, MeshFace},
+    sampling,
+    vector::{Normal3, Point3, Vec3},
+    HitRecord, Hittable, Material, Ray, TOLERANCE,
+};
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Sphere<'a> {
+    id: usize,
+    center: Point3,
+    r: f64,
+    material: &'a Material,
+}
+
+impl<'a> Sphere<'a> {
+    pub(crate) fn new(id: usize, center: Point3, r: f64, material: &'a Material) -> Sphere<'a> {
+        Self {
+            id,
+            center,
+            r,
+            material,
+        }
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.r * self.r
+    }
+
+    /// Point drawn uniformly over the surface, with the outward normal there
+    fn sample(&self, rng: &mut impl Rng) -> (Point3, Normal3) {
+        let normal = sampling::uniform_sphere(rng);
+        (self.center + normal * self.r, Normal3::unit(normal))
+    }
+}
+
+impl Hittable for Sphere<'_> {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let oc = ray.origin - self.center;
+        let a = ray.direction.length_squared();
+        let b = 2.0 * oc.dot(ray.direction);
+        let c = oc.length_squared() - self.r.powf(2.0);
+        let discriminant = b.powf(2.0) - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let left = -b;
+        let right = discriminant.sqrt();
+        let p_root = (left + right) / (2.0 * a);
+        let n_root = (left - right) / (2.0 * a);
+        let t = if n_root > t_min {
+            n_root
+        } else if p_root > t_min {
+            p_root
+        } else {
+            return None;
+        };
+        if t >= t_max {
+            return None;
+        }
+
+        let point = ray.origin + ray.direction * t;
+        let outward = (point - self.center) / self.r;
+        // longitude around y and latitude from the north pole
+        let u = (outward.z.atan2(outward.x) + PI) / (2.0 * PI);
+        let v = outward.y.clamp(-1.0, 1.0).acos() / PI;
+        Some(HitRecord {
+            uv: (u, v),
+            id: self.id,
+            ..HitRecord::new(ray, t, Normal3::unit(outward), self.material)
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        let r = Vec3::splat(self.r);
+        Aabb::new(self.center - r, self.center + r)
+    }
+}
+
+/// One face of a mesh
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Triangle<'a> {
+    id: usize,
+    mesh: &'a Mesh,
+    face: u32,
+    material: &'a Material,
+}
+
+impl<'a> Triangle<'a> {
+    pub(crate) fn new(id: usize, mesh: &'a Mesh, face: usize, material: &'a Material) -> Self {
+        Self {
+            id,
+            mesh,
+            face: face as u32,
+            material,
+        }
+    }
+
+    fn face(&self) -> &MeshFace {
+        &self.mesh.faces[self.face as usize]
+    }
+
+    fn points(&self) -> [Point3; 3] {
+        self.face()
+            .positions
+            .map(|index| self.mesh.positions[index as usize])
+    }
+
+    fn area(&self) -> f64 {
+        let points = self.points();
+        (points[1] - points[0])
+            .cross(points[2] - points[0])
+            .length()
+            / 2.0
+    }
+
+    /// Point drawn uniformly over the face, with its flat normal
+    fn sample(&self, rng: &mut impl Rng) -> (Point3, Normal3) {
+        let points = self.points();
+        let ab = points[1] - points[0];
+        let ac = points[2] - points[0];
+        let root = rng.gen::<f64>().sqrt();
+        let v = rng.gen::<f64>();
+        let point = points[0] + ab * (root * (1.0 - v)) + ac * (root * v);
+        (point, Normal3::unit(ab.cross(ac)))
+    }
+}
+
+impl Hittable for Triangle<'_> {
+    /// Möller-Trumbore
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let points = self.points();
+        let ab = points[1] - points[0];
+        let ac = points[2] - points[0];
+
+        let h = ray.direction.cross(ac);
+
+        let k = ab.dot(h);
+
+        if k.abs() < TOLERANCE {
+            return None;
+        }
+
+        let f = 1.0 / k;
+
+        let s = ray.origin - points[0];
+        let u = f * s.dot(h);
+
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(ab);
+        let v = f * ray.direction.dot(q);
+        if v < 0.0 || (u + v) > 1.0 {
+            return None;
+        }
+
+        let t = f * ac.dot(q);
+
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+
+        let face = self.face();
+        let barycentric = [1.0 - u - v, u, v];
+        let interpolate = |values: [Vec3; 3]| {
+            values[0] * barycentric[0] + values[1] * barycentric[1] + values[2] * barycentric[2]
+        };
+
+        let normal = Normal3::unit(ab.cross(ac));
+        let record = HitRecord::new(ray, t, normal, self.material);
+
+        let shading_normal = match face.normals {
+            Some(indices) => {
+                let normals = indices.map(|index| Vec3::from(self.mesh.normals[index as usize]));
+                // turned towards the geometric normal as a whole rather than
+                // at each point, so that they can still bend past it
+                let sum = normals[0] + normals[1] + normals[2];
+                let normal = Normal3::unit(interpolate(normals));
+                if sum.dot(record.geometric_normal) < 0.0 {
+                    -normal
+                } else {
+                    normal
+                }
+            }
+            None => record.shading_normal,
+        };
+
+        // without texture coordinates uv are the weights of the second and third points
+        let uv = match face.texcoords {
+            Some(indices) => {
+                let texcoords = indices.map(|index| {
+                    let (s, t) = self.mesh.texcoords[index as usize];
+                    Vec3::new(s, t, 0.0)
+                });
+                let uv = interpolate(texcoords);
+                (uv.x, uv.y)
+            }
+            None => (u, v),
+        };
+
+        Some(HitRecord {
+            shading_normal,
+            barycentric,
+            uv,
+            id: self.id,
+            ..record
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.points()
+            .iter()
+            .fold(Aabb::empty(), |bounds, &point| bounds.grow(point))
+    }
+}
+
+/// Everything the acceleration structure holds, the built in primitives are
+/// matched directly and only user geometry goes through dynamic dispatch
+#[derive(Clone, Debug)]
+pub(crate) enum Shape<'a> {
+    Triangle(Triangle<'a>),
+    Sphere(Sphere<'a>),
+    Custom(usize, &'a dyn Hittable),
+}
+
+impl Shape<'_> {
+    pub(crate) fn id(&self) -> usize {
+        match self {
+            Shape::Triangle(triangle) => triangle.id,
+            Shape::Sphere(sphere) => sphere.id,
+            Shape::Custom(id, _) => *id,
+        }
+    }
+
+    /// Unknown for user geometry, which only reports it per hit
+    pub(crate) fn material(&self) -> Option<&Material> {
+        match self {
+            Shape::Triangle(triangle) => Some(triangle.material),
+            Shape::Sphere(sphere) => Some(sphere.material),
+            Shape::Custom(..) => None,
+        }
+    }
+
+    /// Surface area, zero for user geometry which cannot be sampled
+    pub(crate) fn area(&self) -> f64 {
+        match self {
+            Shape::Triangle(triangle) => triangle.area(),
+            Shape::Sphere(sphere) => sphere.area(),
+            Shape::Custom(..) => 0.0,
+        }
+    }
+
+    /// Point drawn uniformly over the surface together with the normal there
+    pub(crate) fn sample(&self, rng: &mut impl Rng) -> Option<(Point3, Normal3)> {
+        match self {
+            Shape::Triangle(triangle) => Some(triangle.sample(rng)),
+            Shape::Sphere(sphere) => Some(sphere.sample(rng)),
+            Shape::Custom(..) => None,
+        }
+    }
+}
+
+impl Hittable for Shape<'_> {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        match self {
+            Shape::Triangle(triangle) => triangle.intersect(ray, t_min, t_max),
+            Shape::Sphere(sphere) => sphere.intersect(ray, t_min, t_max),
+            Shape::Custom(id, hittable) => hittable
+                .intersect(ray, t_min, t_max)
+                .map(|record| HitRecord { id: *id, ..record }),
+        }
+    }
+
+    fn bounds(&self) -> Aabb {
+        match self {
+            Shape::Triangle(triangle) => triangle.bounds(),
+            Shape::Sphere(sphere) => sphere.bounds(),
+            Shape::Custom(_, hittable) => hittable.bounds(),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::f64::consts::PI;
+
+use crate::{
+    environment::{self, Environment},
+    texture::Texture,
+    vector::{Color, Vec3},
+    Light,
+};
+
+/// Scene units per kcd/m² of sky luminance and per klx of sunlight, which
+/// puts a white surface under the noon sun a little below one
+const DAYLIGHT_SCALE: f64 = 1.0 / 50.0;
+
+/// Sunlight above the atmosphere, in klx
+const SOLAR_ILLUMINANCE: f64 = 128.0;
+
+/// Reflectance of the ground shown below the horizon
+const GROUND_ALBEDO: f64 = 0.3;
+
+/// Size of the image the sky is baked into for sampling
+const WIDTH: usize = 512;
+const HEIGHT: usize = 256;
+
+/// Clear daylight sky following Preetham, Shirley and Smits, "A Practical
+/// Analytic Model for Daylight". The sun sits `elevation` degrees above the
+/// horizon, `azimuth` degrees from +z towards +x, and `turbidity` measures
+/// the haze from 2 for a very clear sky to 10 for a hazy one. The model was
+/// fitted to that range and breaks down outside it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sky {
+    pub elevation: f64,
+    pub azimuth: f64,
+    pub turbidity: f64,
+}
+
+impl Sky {
+    /// Unit vector towards the sun
+    pub fn sun_direction(&self) -> Vec3 {
+        let (elevation, azimuth) = (self.elevation.to_radians(), self.azimuth.to_radians());
+        Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        )
+    }
+
+    /// The sky as an environment light, the sun itself left out, scaled by
+    /// `intensity`. Below the horizon lies a plain grey ground lit by both
+    pub fn environment(&self, intensity: f64) -> Environment {
+        let perez = Perez::new(self);
+        let mut texels = Vec::with_capacity(WIDTH * HEIGHT);
+        // irradiance on the ground from the sky, summed over the texels
+        let mut irradiance = Color::default();
+        let solid_angle = 2.0 * PI * PI / (WIDTH * HEIGHT) as f64;
+        for j in 0..HEIGHT {
+            for i in 0..WIDTH {
+                let u = (i as f64 + 0.5) / WIDTH as f64;
+                let v = 1.0 - (j as f64 + 0.5) / HEIGHT as f64;
+                let direction = environment::direction((u, v));
+                let radiance = perez.radiance(direction) * DAYLIGHT_SCALE;
+                let sin = (1.0 - direction.y * direction.y).sqrt();
+                irradiance += radiance * (direction.y.max(0.0) * sin * solid_angle);
+                texels.push(radiance);
+            }
+        }
+
+        if let Light::Directional {
+            intensity, color, ..
+        } = self.sun(1.0)
+        {
+            let sin = self.elevation.to_radians().sin().max(0.0);
+            irradiance += color * intensity * sin;
+        }
+        let ground = irradiance * GROUND_ALBEDO / PI;
+        for (j, row) in texels.chunks_mut(WIDTH).enumerate() {
+            if j >= HEIGHT / 2 {
+                row.fill(ground);
+            }
+        }
+
+        Environment::new(Texture::new(WIDTH, HEIGHT, texels), 0.0, intensity)
+    }
+
+    /// Parallel sunlight coming down from the sun, reddened by the air it
+    /// crosses on the way, scaled by `intensity`. Dark once the sun has set
+    pub fn sun(&self, intensity: f64) -> Light {
+        let intensity = if self.elevation > 0.0 { intensity } else { 0.0 };
+        let elevation = self.elevation.max(0.0);
+        // Kasten and Young's relative air mass
+        let zenith = 90.0 - elevation;
+        let air_mass =
+            1.0 / (zenith.to_radians().cos() + 0.50572 * (96.07995 - zenith).powf(-1.6364));
+
+        // Rayleigh and aerosol optical depths at the centers of the red, green
+        // and blue bands, in micrometres, with Ångström's turbidity formula
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = [0.68, 0.55, 0.44].map(|wavelength: f64| {
+            let rayleigh = 0.008735 * wavelength.powf(-4.08);
+            let aerosol = beta * wavelength.powf(-1.3);
+            (-air_mass * (rayleigh + aerosol)).exp()
+        });
+
+        Light::Directional {
+            direction: -self.sun_direction(),
+            intensity: SOLAR_ILLUMINANCE * DAYLIGHT_SCALE * intensity,
+            color: Color::from(transmittance),
+        }
+    }
+}
+
+/// The Perez luminance distribution fitted to turbidity and the sun
+/// position, for luminance Y and the chromaticities x and y
+struct Perez {
+    sun: Vec3,
+    /// Sun zenith angle
+    theta_s: f64,
+    zenith: [f64; 3],
+    coefficients: [[f64; 5]; 3],
+}
+
+impl Perez {
+    fn new(sky: &Sky) -> Self {
+        let t = sky.turbidity;
+        // the fit breaks down once the sun sets, keep it on the horizon
+        let theta_s = (90.0 - sky.elevation.max(0.0))
+            .to_radians()
+            .min(PI / 2.0 - 1E-3);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let (t2, s, s2, s3) = (t * t, theta_s, theta_s * theta_s, theta_s.powi(3));
+        let x = t2 * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+            + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+            + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+        let y = t2 * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+            + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+            + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        Self {
+            sun: sky.sun_direction(),
+            theta_s,
+            zenith: [luminance, x, y],
+            coefficients,
+        }
+    }
+
+    /// Linear sRGB radiance in kcd/m² arriving from the unit `direction`,
+    /// nothing from below the horizon
+    fn radiance(&self, direction: Vec3) -> Color {
+        if direction.y <= 0.0 {
+            return Color::default();
+        }
+        let theta = direction.y.min(1.0).acos();
+        let gamma = direction.dot(self.sun).clamp(-1.0, 1.0).acos();
+
+        let [luminance, x, y] = [0, 1, 2].map(|k| {
+            let f = |theta: f64, gamma: f64| {
+                let [a, b, c, d, e] = self.coefficients[k];
+                let cos = gamma.cos();
+                (1.0 + a * (b / theta.cos().max(1E-2)).exp())
+                    * (1.0 + c * (d * gamma).exp() + e * cos * cos)
+            };
+            self.zenith[k] * f(theta, gamma) / f(0.0, self.theta_s)
+        });
+
+        // xyY to XYZ to linear sRGB
+        let big_x = x * luminance / y;
+        let big_z = (1.0 - x - y) * luminance / y;
+        Color::new(
+            3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+            -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+            0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+        )
+        .max(Color::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zenith_is_bright_over_the_whole_turbidity_range() {
+        for turbidity in (4..=20).map(|t| t as f64 / 2.0) {
+            for elevation in (0..=90).step_by(5) {
+                let sky = Sky {
+                    elevation: elevation as f64,
+                    azimuth: 0.0,
+                    turbidity,
+                };
+                let zenith = Perez::new(&sky).radiance(Vec3::new(0.0, 1.0, 0.0));
+                assert!(
+                    zenith.r > 0.0 && zenith.g > 0.0 && zenith.b > 0.0,
+                    "zenith {zenith:?} at turbidity {turbidity} and elevation {elevation}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use image::ImageResult;
+
+use crate::vector::Color;
+
+/// An image wrapped around a surface. Texels are used as stored, the same
+/// way the renderer writes its output without any gamma encoding
+#[derive(Clone, Debug, PartialEq)]
+pub struct Texture {
+    width: usize,
+    height: usize,
+    texels: Vec<Color>,
+}
+
+impl Texture {
+    /// `texels` row by row from the top left corner
+    pub(crate) fn new(width: usize, height: usize, texels: Vec<Color>) -> Texture {
+        Texture {
+            width,
+            height,
+            texels,
+        }
+    }
+
+    pub fn load(path: &Path) -> ImageResult<Texture> {
+        let image = image::open(path)?.into_rgb32f();
+        let texels = image
+            .pixels()
+            .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64))
+            .collect();
+        Ok(Texture {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            texels,
+        })
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    pub(crate) fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Texel in column `i` of row `j`, rows counted from the top
+    pub(crate) fn texel(&self, i: usize, j: usize) -> Color {
+        self.texels[j * self.width + i]
+    }
+
+    /// Bilinear lookup repeating the image in both directions, `v` grows
+    /// upwards as in OBJ texture coordinates
+    pub fn sample(&self, uv: (f64, f64)) -> Color {
+        self.bilinear(uv, |j| j.rem_euclid(self.height as isize))
+    }
+
+    /// Bilinear lookup repeating the image sideways only, for images whose
+    /// top and bottom rows lie at opposite poles
+    pub(crate) fn sample_rows_clamped(&self, uv: (f64, f64)) -> Color {
+        self.bilinear(uv, |j| j.clamp(0, self.height as isize - 1))
+    }
+
+    /// `row` turns a row index that may lie outside the image into one inside
+    fn bilinear(&self, (u, v): (f64, f64), row: impl Fn(isize) -> isize) -> Color {
+        let x = u * self.width as f64 - 0.5;
+        let y = (1.0 - v) * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let texel = |i: f64, j: f64| {
+            let i = (i as isize).rem_euclid(self.width as isize) as usize;
+            let j = row(j as isize) as usize;
+            self.texels[j * self.width + i]
+        };
+
+        let top = texel(x0, y0) * (1.0 - fx) + texel(x0 + 1.0, y0) * fx;
+        let bottom = texel(x0, y0 + 1.0) * (1.0 - fx) + texel(x0 + 1.0, y0 + 1.0) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
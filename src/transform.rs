@@ -0,0 +1,106 @@
+use crate::vector::{Normal3, Point3, Vec3};
+
+/// Affine transforms acting on row vectors, `p * M`, so that `mul(a, b)`
+/// applies `a` first and `b` second
+pub type Matrix4x4 = [[f64; 4]; 4];
+
+pub const IDENTITY: Matrix4x4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+pub fn mul(a: Matrix4x4, b: Matrix4x4) -> Matrix4x4 {
+    let mut m = [
+        [0.0, 0.0, 0.0, 0.0],
+        [0.0, 0.0, 0.0, 0.0],
+        [0.0, 0.0, 0.0, 0.0],
+        [0.0, 0.0, 0.0, 0.0],
+    ];
+
+    for i in 0..4 {
+        for j in 0..4 {
+            m[i][j] = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j] + a[i][3] * b[3][j];
+        }
+    }
+
+    m
+}
+
+pub fn translation(offset: Vec3) -> Matrix4x4 {
+    [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [offset.x, offset.y, offset.z, 1.0],
+    ]
+}
+
+pub fn scaling(factors: Vec3) -> Matrix4x4 {
+    [
+        [factors.x, 0.0, 0.0, 0.0],
+        [0.0, factors.y, 0.0, 0.0],
+        [0.0, 0.0, factors.z, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+pub fn rotation_x(angle: f64) -> Matrix4x4 {
+    let (sin, cos) = angle.sin_cos();
+    [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, cos, sin, 0.0],
+        [0.0, -sin, cos, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+pub fn rotation_y(angle: f64) -> Matrix4x4 {
+    let (sin, cos) = angle.sin_cos();
+    [
+        [cos, 0.0, -sin, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [sin, 0.0, cos, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+pub fn rotation_z(angle: f64) -> Matrix4x4 {
+    let (sin, cos) = angle.sin_cos();
+    [
+        [cos, sin, 0.0, 0.0],
+        [-sin, cos, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+pub fn transform_point(m: &Matrix4x4, p: Point3) -> Point3 {
+    let a = p.x * m[0][0] + p.y * m[1][0] + p.z * m[2][0] + m[3][0];
+    let b = p.x * m[0][1] + p.y * m[1][1] + p.z * m[2][1] + m[3][1];
+    let c = p.x * m[0][2] + p.y * m[1][2] + p.z * m[2][2] + m[3][2];
+    let w = p.x * m[0][3] + p.y * m[1][3] + p.z * m[2][3] + m[3][3];
+    Point3::new(a / w, b / w, c / w)
+}
+
+/// Transforms a surface normal by the inverse transpose of the linear part
+/// of `m`, so it stays perpendicular to transformed tangents, and rescales
+/// it to unit length
+pub fn transform_normal(m: &Matrix4x4, n: Normal3) -> Normal3 {
+    let rows = [0, 1, 2].map(|i| Vec3::new(m[i][0], m[i][1], m[i][2]));
+    // the cofactor matrix, row by row, is the inverse transpose times the determinant
+    let cofactors = [
+        rows[1].cross(rows[2]),
+        rows[2].cross(rows[0]),
+        rows[0].cross(rows[1]),
+    ];
+    let determinant = rows[0].dot(cofactors[0]);
+    let normal = cofactors[0] * n.x + cofactors[1] * n.y + cofactors[2] * n.z;
+    let normal = Normal3::unit(normal);
+    if determinant < 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
@@ -1,94 +1,420 @@
-#[derive(Clone, Debug, PartialEq)]
-pub struct Vector {
-    pub coords: Vec<f64>,
+use std::{
+    iter::Sum,
+    ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// A position in space. Points differ by a `Vec3` and move by one, but
+/// cannot be added to each other or scaled
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Point3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// A unit direction perpendicular to a surface, made with `Normal3::unit`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Normal3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
 }
 
-impl Vector {
-    pub fn new(coords: Vec<f64>) -> Self {
-        Self { coords }
+/// Linear RGB radiance or reflectance. Kept apart from `Vec3` so that
+/// geometry and light cannot be mixed up
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+}
+
+impl Vec3 {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
     }
 
-    pub fn new_xyz(x: f64, y: f64, z: f64) -> Self {
+    pub const fn splat(v: f64) -> Self {
+        Self { x: v, y: v, z: v }
+    }
+
+    pub fn length(self) -> f64 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn length_squared(self) -> f64 {
+        self.dot(self)
+    }
+
+    pub fn dot(self, other: impl Into<Vec3>) -> f64 {
+        let other = other.into();
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Vec3) -> Vec3 {
         Self {
-            coords: vec![x, y, z],
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
         }
     }
 
-    pub fn length(&self) -> f64 {
-        (self.length_squared()).sqrt()
+    pub fn unit(self) -> Vec3 {
+        self / self.length()
     }
 
-    pub fn length_squared(&self) -> f64 {
-        self.dot(self)
+    pub fn min(self, other: Vec3) -> Vec3 {
+        Self::new(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.z.min(other.z),
+        )
+    }
+
+    pub fn max(self, other: Vec3) -> Vec3 {
+        Self::new(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.z.max(other.z),
+        )
     }
 
-    pub fn dot(&self, other: &Vector) -> f64 {
-        self.coords
-            .iter()
-            .zip(other.coords.iter())
-            .map(|(a, b)| a * b)
-            .sum()
+    pub fn max_element(self) -> f64 {
+        self.x.max(self.y).max(self.z)
     }
 
-    pub fn cross(&self, other: &Vector) -> Vector {
-        let s = &self.coords;
-        let o = &other.coords;
-        let x = s[1] * o[2] - s[2] * o[1];
-        let y = s[2] * o[0] - s[0] * o[2];
-        let z = s[0] * o[1] - s[1] * o[0];
+    pub fn map(self, f: impl Fn(f64) -> f64) -> Vec3 {
+        Self::new(f(self.x), f(self.y), f(self.z))
+    }
+}
 
-        Self::new(vec![x, y, z])
+impl From<[f64; 3]> for Vec3 {
+    fn from([x, y, z]: [f64; 3]) -> Self {
+        Self::new(x, y, z)
     }
+}
 
-    pub fn unit(&self) -> Vector {
-        self.div_float(self.length())
+impl Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
     }
+}
 
-    pub fn mul_float(&self, other: f64) -> Self {
-        let coords = self.coords.iter().map(|a| a * other).collect();
-        Self::new(coords)
+impl Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
     }
+}
+
+impl Mul<f64> for Vec3 {
+    type Output = Vec3;
 
-    pub fn div_float(&self, other: f64) -> Self {
-        let coords = self.coords.iter().map(|a| a / other).collect();
-        Self::new(coords)
+    fn mul(self, other: f64) -> Vec3 {
+        Vec3::new(self.x * other, self.y * other, self.z * other)
     }
+}
+
+impl Mul<Vec3> for f64 {
+    type Output = Vec3;
 
-    pub fn add_vec(&self, other: &Vector) -> Self {
-        let coords = self
-            .coords
-            .iter()
-            .zip(other.coords.iter())
-            .map(|(a, b)| a + b)
-            .collect();
-        Self::new(coords)
+    fn mul(self, other: Vec3) -> Vec3 {
+        other * self
     }
+}
+
+impl Div<f64> for Vec3 {
+    type Output = Vec3;
 
-    pub fn sub_vec(&self, other: &Vector) -> Self {
-        let coords = self
-            .coords
-            .iter()
-            .zip(other.coords.iter())
-            .map(|(a, b)| a - b)
-            .collect();
-        Self::new(coords)
+    fn div(self, other: f64) -> Vec3 {
+        Vec3::new(self.x / other, self.y / other, self.z / other)
     }
+}
 
-    pub fn mul_vec(&self, other: &Vector) -> Self {
-        let coords = self
-            .coords
-            .iter()
-            .zip(other.coords.iter())
-            .map(|(a, b)| a * b)
-            .collect();
-        Self::new(coords)
+impl Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3::new(-self.x, -self.y, -self.z)
     }
 }
 
-impl Default for Vector {
-    fn default() -> Self {
+impl AddAssign for Vec3 {
+    fn add_assign(&mut self, other: Vec3) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Vec3 {
+    fn sub_assign(&mut self, other: Vec3) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign<f64> for Vec3 {
+    fn mul_assign(&mut self, other: f64) {
+        *self = *self * other;
+    }
+}
+
+impl DivAssign<f64> for Vec3 {
+    fn div_assign(&mut self, other: f64) {
+        *self = *self / other;
+    }
+}
+
+impl Sum for Vec3 {
+    fn sum<I: Iterator<Item = Vec3>>(iter: I) -> Vec3 {
+        iter.fold(Vec3::default(), |sum, v| sum + v)
+    }
+}
+
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &f64 {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 index out of bounds: {index}"),
+        }
+    }
+}
+
+impl IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, index: usize) -> &mut f64 {
+        match index {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Vec3 index out of bounds: {index}"),
+        }
+    }
+}
+
+impl Color {
+    pub const fn new(r: f64, g: f64, b: f64) -> Self {
+        Self { r, g, b }
+    }
+
+    pub const fn splat(v: f64) -> Self {
+        Self { r: v, g: v, b: v }
+    }
+
+    pub fn max(self, other: Color) -> Color {
+        Self::new(
+            self.r.max(other.r),
+            self.g.max(other.g),
+            self.b.max(other.b),
+        )
+    }
+
+    pub fn max_element(self) -> f64 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    /// Mean of the three channels
+    pub fn average(self) -> f64 {
+        (self.r + self.g + self.b) / 3.0
+    }
+
+    pub fn map(self, f: impl Fn(f64) -> f64) -> Color {
+        Self::new(f(self.r), f(self.g), f(self.b))
+    }
+}
+
+impl From<[f64; 3]> for Color {
+    fn from([r, g, b]: [f64; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+impl Add for Color {
+    type Output = Color;
+
+    fn add(self, other: Color) -> Color {
+        Color::new(self.r + other.r, self.g + other.g, self.b + other.b)
+    }
+}
+
+/// Channel by channel, a surface filtering the light it receives
+impl Mul for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        Color::new(self.r * other.r, self.g * other.g, self.b * other.b)
+    }
+}
+
+impl Mul<f64> for Color {
+    type Output = Color;
+
+    fn mul(self, other: f64) -> Color {
+        Color::new(self.r * other, self.g * other, self.b * other)
+    }
+}
+
+impl Div<f64> for Color {
+    type Output = Color;
+
+    fn div(self, other: f64) -> Color {
+        Color::new(self.r / other, self.g / other, self.b / other)
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, other: Color) {
+        *self = *self + other;
+    }
+}
+
+impl MulAssign<f64> for Color {
+    fn mul_assign(&mut self, other: f64) {
+        *self = *self * other;
+    }
+}
+
+impl DivAssign<f64> for Color {
+    fn div_assign(&mut self, other: f64) {
+        *self = *self / other;
+    }
+}
+
+impl Sum for Color {
+    fn sum<I: Iterator<Item = Color>>(iter: I) -> Color {
+        iter.fold(Color::default(), |sum, c| sum + c)
+    }
+}
+
+impl Point3 {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+
+    pub const fn splat(v: f64) -> Self {
+        Self { x: v, y: v, z: v }
+    }
+
+    pub fn min(self, other: Point3) -> Point3 {
+        Self::new(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.z.min(other.z),
+        )
+    }
+
+    pub fn max(self, other: Point3) -> Point3 {
+        Self::new(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.z.max(other.z),
+        )
+    }
+
+    /// The point `t` of the way from `self` to `other`
+    pub fn lerp(self, other: Point3, t: f64) -> Point3 {
+        self + (other - self) * t
+    }
+}
+
+impl From<[f64; 3]> for Point3 {
+    fn from([x, y, z]: [f64; 3]) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl Sub for Point3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Point3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Add<Vec3> for Point3 {
+    type Output = Point3;
+
+    fn add(self, other: Vec3) -> Point3 {
+        Point3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub<Vec3> for Point3 {
+    type Output = Point3;
+
+    fn sub(self, other: Vec3) -> Point3 {
+        Point3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl AddAssign<Vec3> for Point3 {
+    fn add_assign(&mut self, other: Vec3) {
+        *self = *self + other;
+    }
+}
+
+impl Index<usize> for Point3 {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &f64 {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Point3 index out of bounds: {index}"),
+        }
+    }
+}
+
+impl Normal3 {
+    /// The normal along `v`, which must not be zero
+    pub fn unit(v: Vec3) -> Normal3 {
+        let v = v.unit();
         Self {
-            coords: vec![0.0, 0.0, 0.0],
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+
+    pub fn dot(self, other: Vec3) -> f64 {
+        Vec3::from(self).dot(other)
+    }
+}
+
+impl From<Normal3> for Vec3 {
+    fn from(normal: Normal3) -> Self {
+        Vec3::new(normal.x, normal.y, normal.z)
+    }
+}
+
+impl Mul<f64> for Normal3 {
+    type Output = Vec3;
+
+    fn mul(self, other: f64) -> Vec3 {
+        Vec3::from(self) * other
+    }
+}
+
+impl Neg for Normal3 {
+    type Output = Normal3;
+
+    fn neg(self) -> Normal3 {
+        Normal3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
         }
     }
 }